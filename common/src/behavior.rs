@@ -0,0 +1,583 @@
+use std::cell::OnceCell;
+
+use pathfinding::prelude::astar;
+use rand::Rng;
+
+use crate::{
+    being::{Being, PackRole},
+    level::Level,
+    monsterkind::AiKind,
+    point::Point,
+    tile::{DoorState, Tile},
+    tilearray::TileArray,
+    trap::Trap,
+    GameRng, PACK_RADIUS, SEARCH_RADIUS,
+};
+
+// What an enemy wants to do on its turn
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Intent {
+    Wait,
+    MoveTo(Point),
+    AttackPlayer,
+    // Attack the player from a distance
+    ShootPlayer,
+}
+
+// What an enemy knows about the world when deciding what to do
+pub struct World<'a> {
+    pub level: &'a Level,
+    pub player: &'a Being,
+    pub current_turn: u32,
+    // Worked out the first time someone flees during the player's turn, see Level::flee_map
+    pub flee_map: &'a OnceCell<TileArray<i32>>,
+}
+impl World<'_> {
+    pub fn flee_map(&self) -> &TileArray<i32> {
+        self.flee_map
+            .get_or_init(|| self.level.flee_map(self.player.position))
+    }
+    pub fn sees_player(&self, being: &Being) -> bool {
+        self.level.last_seen[being.position] == self.current_turn
+    }
+    pub fn can_attack_player(&self, being: &Being) -> bool {
+        being.position.is_neighboring(self.player.position)
+            && self.level.can_step(being.position, self.player.position)
+    }
+    // Whether the player is within range and a projectile can reach them,
+    // neither walls nor other enemies being in the way
+    pub fn can_shoot_player(&self, being: &Being, range: u32) -> bool {
+        let line = being.position.line_to(self.player.position);
+        being.position.distance_to(self.player.position) <= range
+            && self
+                .level
+                .tiles
+                .has_line_of_fire(being.position, self.player.position)
+            && line[..line.len() - 1].iter().all(|&point| {
+                self.level
+                    .enemies
+                    .iter()
+                    .all(|enemy| enemy.position != point)
+            })
+    }
+    // Whether the being can move from a point to a neighboring one, i.e. there's nobody there.
+    // The monsters that can open doors get through closed ones too, opening them on the way.
+    pub fn can_move(&self, being: &Being, from: Point, to: Point) -> bool {
+        (self.level.can_step(from, to) && self.is_free(to))
+            || (being
+                .kind
+                .monster()
+                .is_some_and(|monster| monster.opens_doors)
+                && self.level.tiles.in_bounds(to)
+                && self.level.tiles[to] == Tile::Door(DoorState::Closed)
+                && !from.is_diagonal_to(to))
+    }
+    // Whether a point can be walked on and there's nobody there, nor any trap the player found.
+    // Going around the hidden ones would give them away.
+    pub fn is_free(&self, point: Point) -> bool {
+        self.level.tiles.in_bounds(point)
+            && self.level.tiles[point].is_walkable()
+            && !matches!(
+                self.level.tiles[point],
+                Tile::Trap(Trap { found: true, .. })
+            )
+            && point != self.player.position
+            && self
+                .level
+                .enemies
+                .iter()
+                .all(|enemy| enemy.position != point)
+    }
+    pub fn pack_mates<'b>(&'b self, being: &'b Being) -> impl Iterator<Item = &'b Being> {
+        self.level
+            .enemies
+            .iter()
+            .filter(move |enemy| enemy.is_pack_mate_of(being))
+    }
+    pub fn pack_leader<'b>(&'b self, being: &'b Being) -> Option<&'b Being> {
+        self.pack_mates(being)
+            .find(|mate| mate.pack.map(|pack| pack.role) == Some(PackRole::Leader))
+    }
+}
+
+pub trait Behavior {
+    fn decide(&self, being: &Being, world: &World, rng: &mut GameRng) -> Intent;
+    // Whether the being keeps a memory of where it last saw the player
+    fn hunts(&self) -> bool {
+        false
+    }
+}
+
+// The first step on the shortest path to the target, attacking the player if they're there
+fn step_towards(being: &Being, target: Point, world: &World) -> Intent {
+    let player_position = world.player.position;
+    match astar(
+        &being.position,
+        |&point| {
+            point
+                .iter_neighbors()
+                .filter(move |&p| {
+                    (p == player_position && world.level.can_step(point, p))
+                        || world.can_move(being, point, p)
+                })
+                .map(|p| (p, 1))
+        },
+        |&point| point.distance_to(target),
+        |&point| point == target,
+    ) {
+        Some((path, _)) if path.len() < 2 => Intent::Wait,
+        Some((path, _)) if path[1] == player_position => Intent::AttackPlayer,
+        Some((path, _)) => Intent::MoveTo(path[1]),
+        None => Intent::Wait,
+    }
+}
+// A step in a random direction, staying within the given distance of the center
+fn random_step(
+    being: &Being,
+    center: Point,
+    distance: u32,
+    world: &World,
+    rng: &mut GameRng,
+) -> Intent {
+    let possible_positions: Vec<Point> = being
+        .position
+        .iter_neighbors()
+        .filter(|&p| world.can_move(being, being.position, p))
+        .filter(|&p| p.distance_to(center) <= distance)
+        .collect();
+    if possible_positions.is_empty() {
+        Intent::Wait
+    } else {
+        Intent::MoveTo(possible_positions[rng.gen_range(0, possible_positions.len())])
+    }
+}
+
+// Goes for a free spot next to the target that none of its pack mates is closer to,
+// so that the pack surrounds the player instead of queueing up behind each other
+fn surround(being: &Being, target: Point, world: &World) -> Intent {
+    if world.can_attack_player(being) {
+        return Intent::AttackPlayer;
+    }
+    let mates: Vec<&Being> = world.pack_mates(being).collect();
+    if mates.is_empty() {
+        return step_towards(being, target, world);
+    }
+
+    match target
+        .iter_neighbors()
+        .filter(|&spot| world.is_free(spot) && world.level.can_step(spot, target))
+        .min_by_key(|&spot| {
+            let distance = being.position.distance_to(spot);
+            let closer_mates = mates
+                .iter()
+                .filter(|mate| mate.position.distance_to(spot) < distance)
+                .count();
+            (closer_mates, distance)
+        }) {
+        Some(spot) => step_towards(being, spot, world),
+        None => step_towards(being, target, world),
+    }
+}
+
+// The step that takes the being furthest away from the player, if any
+fn retreat(being: &Being, world: &World) -> Option<Point> {
+    let flee_map = world.flee_map();
+
+    being
+        .position
+        .iter_neighbors()
+        .filter(|&p| world.can_move(being, being.position, p))
+        .filter(|&p| flee_map[p] < flee_map[being.position])
+        .min_by_key(|&p| flee_map[p])
+}
+
+// Goes for the player when in sight, or where the being or its pack mates last saw them,
+// searching around there for a while before giving up.
+// In a pack, it surrounds the player along with its mates, and otherwise keeps close to its leader.
+fn hunt(being: &Being, world: &World, rng: &mut GameRng) -> Intent {
+    if world.sees_player(being) {
+        return surround(being, world.player.position, world);
+    }
+
+    match being.memory {
+        Some(memory) if memory.searching => {
+            random_step(being, memory.player_position, SEARCH_RADIUS, world, rng)
+        }
+        Some(memory) => surround(being, memory.player_position, world),
+        None => match world.pack_leader(being) {
+            Some(leader) if being.position.distance_to(leader.position) > PACK_RADIUS => {
+                match leader
+                    .position
+                    .iter_neighbors()
+                    .filter(|&spot| world.is_free(spot))
+                    .min_by_key(|&spot| being.position.distance_to(spot))
+                {
+                    Some(spot) => step_towards(being, spot, world),
+                    None => Intent::Wait,
+                }
+            }
+            Some(leader) => random_step(being, leader.position, PACK_RADIUS, world, rng),
+            None => random_step(being, being.position, 1, world, rng),
+        },
+    }
+}
+
+// Chases the player when in sight, then hunts them down where they were last seen.
+// After searching there for a while, it gives up and wanders around.
+pub struct Chaser;
+impl Behavior for Chaser {
+    fn decide(&self, being: &Being, world: &World, rng: &mut GameRng) -> Intent {
+        hunt(being, world, rng)
+    }
+    fn hunts(&self) -> bool {
+        true
+    }
+}
+
+// Moves around chaotically, attacking the player when next to them.
+// A swarm hunts the player together like a pack of Chasers, though.
+pub struct Wanderer;
+impl Behavior for Wanderer {
+    fn decide(&self, being: &Being, world: &World, rng: &mut GameRng) -> Intent {
+        if being.pack.is_some() {
+            return hunt(being, world, rng);
+        }
+        if world.can_attack_player(being) {
+            return Intent::AttackPlayer;
+        }
+
+        random_step(being, being.position, 1, world, rng)
+    }
+}
+
+// Stays in place, attacking the player when next to them
+pub struct Static;
+impl Behavior for Static {
+    fn decide(&self, being: &Being, world: &World, _rng: &mut GameRng) -> Intent {
+        if world.can_attack_player(being) {
+            Intent::AttackPlayer
+        } else {
+            Intent::Wait
+        }
+    }
+}
+
+// Shoots at the player from its range, backing off when they come closer than it likes.
+// Out of sight, it hunts the player down like a Chaser.
+pub struct Ranged;
+impl Behavior for Ranged {
+    fn decide(&self, being: &Being, world: &World, rng: &mut GameRng) -> Intent {
+        let (range, preferred_range) = match being.kind.monster() {
+            Some(monster) => (monster.range, monster.preferred_range),
+            None => return Intent::Wait,
+        };
+
+        if world.sees_player(being) {
+            if being.position.distance_to(world.player.position) < preferred_range {
+                if let Some(position) = retreat(being, world) {
+                    return Intent::MoveTo(position);
+                }
+            }
+            if world.can_shoot_player(being, range) {
+                return Intent::ShootPlayer;
+            }
+            return step_towards(being, world.player.position, world);
+        }
+
+        match being.memory {
+            Some(memory) if memory.searching => {
+                random_step(being, memory.player_position, SEARCH_RADIUS, world, rng)
+            }
+            Some(memory) => step_towards(being, memory.player_position, world),
+            None => random_step(being, being.position, 1, world, rng),
+        }
+    }
+    fn hunts(&self) -> bool {
+        true
+    }
+}
+
+// Runs away from the player along the level's flee map,
+// used for frightened or demoralized enemies whatever their usual behavior.
+// Cornered, it fights back.
+pub struct Fleeing;
+impl Behavior for Fleeing {
+    fn decide(&self, being: &Being, world: &World, _rng: &mut GameRng) -> Intent {
+        match retreat(being, world) {
+            Some(flee_position) => Intent::MoveTo(flee_position),
+            None if world.can_attack_player(being) => Intent::AttackPlayer,
+            None => Intent::Wait,
+        }
+    }
+}
+
+impl AiKind {
+    pub fn behavior(self) -> &'static dyn Behavior {
+        match self {
+            AiKind::Chaser => &Chaser,
+            AiKind::Wanderer => &Wanderer,
+            AiKind::Static => &Static,
+            AiKind::Ranged => &Ranged,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::{
+        being::{Memory, PackMember},
+        beingkind::BeingKind,
+        gamecontext::{tests::arena, GameContext},
+        tile::Tile,
+        tilearray::TileArray,
+        trap::{Trap, TrapKind},
+    };
+
+    // An arena with a Gnoll at the given offset from the player
+    fn enemy_at(offset: Point) -> (Being, GameContext) {
+        monster_at("Gnoll", offset)
+    }
+    fn monster_at(name: &str, offset: Point) -> (Being, GameContext) {
+        let mut context = arena();
+        let enemy = Being::new_of_kind(
+            BeingKind::named(name).unwrap(),
+            context.player.position + offset,
+            &mut GameRng::seed_from_u64(0),
+        );
+        context.level.enemies.push(enemy.clone());
+
+        (enemy, context)
+    }
+    // What the enemies of the context know about it
+    fn world(context: &GameContext) -> World<'_> {
+        World {
+            level: &context.level,
+            player: &context.player,
+            current_turn: context.current_turn,
+            flee_map: &context.flee_map,
+        }
+    }
+    fn decide(behavior: &dyn Behavior, offset: Point) -> Intent {
+        let (enemy, context) = enemy_at(offset);
+        let world = world(&context);
+
+        behavior.decide(&enemy, &world, &mut GameRng::seed_from_u64(0))
+    }
+
+    #[test]
+    fn behavior_chaser() {
+        assert_eq!(decide(&Chaser, Point::new(1, 1)), Intent::AttackPlayer);
+
+        let (_, context) = enemy_at(Point::new(3, 0));
+        match decide(&Chaser, Point::new(3, 0)) {
+            Intent::MoveTo(position) => {
+                assert_eq!(position.distance_to(context.player.position), 2)
+            }
+            intent => panic!("{:?}", intent),
+        }
+    }
+    #[test]
+    fn behavior_chaser_hunts_the_player() {
+        let (mut enemy, mut context) = enemy_at(Point::new(3, 0));
+        context.level.last_seen =
+            TileArray::new(context.level.tiles.width(), context.level.tiles.height(), 0);
+        let target = enemy.position + Point::new(0, 5);
+        let decide = |enemy: &Being, context: &GameContext| {
+            let world = world(context);
+            Chaser.decide(enemy, &world, &mut GameRng::seed_from_u64(0))
+        };
+
+        // Out of sight, it goes to where the player was last seen
+        enemy.memory = Some(Memory::new(target));
+        assert_eq!(
+            decide(&enemy, &context),
+            Intent::MoveTo(enemy.position + Point::new(0, 1))
+        );
+
+        // Then it searches around there
+        enemy.position = target;
+        enemy.memory = Some(Memory {
+            searching: true,
+            ..Memory::new(target)
+        });
+        for _ in 0..10 {
+            match decide(&enemy, &context) {
+                Intent::MoveTo(position) => {
+                    assert!(position.distance_to(target) <= SEARCH_RADIUS);
+                    enemy.position = position;
+                }
+                intent => panic!("{:?}", intent),
+            }
+        }
+    }
+    #[test]
+    fn behavior_chaser_surrounds_the_player() {
+        let (mut leader, mut context) = enemy_at(Point::new(3, 0));
+        leader.pack = Some(PackMember {
+            pack: 0,
+            member: 0,
+            role: PackRole::Leader,
+        });
+        let mut follower = leader.clone();
+        follower.position = leader.position + Point::new(1, 0);
+        follower.pack = Some(PackMember {
+            pack: 0,
+            member: 1,
+            role: PackRole::Follower,
+        });
+        context.level.enemies = vec![leader.clone(), follower.clone()];
+        let world = world(&context);
+
+        // The follower goes around its leader instead of queueing up behind it
+        match Chaser.decide(&follower, &world, &mut GameRng::seed_from_u64(0)) {
+            Intent::MoveTo(position) => assert_ne!(position.y, leader.position.y),
+            intent => panic!("{:?}", intent),
+        }
+    }
+    #[test]
+    fn behavior_avoids_traps() {
+        let (enemy, mut context) = enemy_at(Point::new(3, 0));
+        for neighbor in enemy.position.iter_neighbors() {
+            context.level.tiles[neighbor] = Tile::Trap(Trap {
+                kind: TrapKind::Alarm,
+                found: true,
+            });
+        }
+        let world = world(&context);
+
+        assert_eq!(
+            Chaser.decide(&enemy, &world, &mut GameRng::seed_from_u64(0)),
+            Intent::Wait
+        );
+        assert_eq!(
+            Wanderer.decide(&enemy, &world, &mut GameRng::seed_from_u64(0)),
+            Intent::Wait
+        );
+    }
+    #[test]
+    fn behavior_walks_over_hidden_traps() {
+        let (enemy, mut context) = enemy_at(Point::new(3, 0));
+        let player_position = context.player.position;
+        for point in context.level.tiles.points().collect::<Vec<_>>() {
+            if point.y != player_position.y
+                || !(player_position.x..=enemy.position.x).contains(&point.x)
+            {
+                context.level.tiles[point] = Tile::Wall;
+            }
+        }
+        let trap = player_position + Point::new(2, 0);
+        context.level.tiles[trap] = Tile::Trap(Trap::hidden(TrapKind::Dart));
+        let decide = |context: &GameContext| {
+            let world = world(context);
+            Chaser.decide(&enemy, &world, &mut GameRng::seed_from_u64(0))
+        };
+
+        // A trap the player hasn't found doesn't cut the corridor off
+        assert_eq!(decide(&context), Intent::MoveTo(trap));
+
+        context.level.tiles[trap] = Tile::Trap(Trap {
+            kind: TrapKind::Dart,
+            found: true,
+        });
+        assert_eq!(decide(&context), Intent::Wait);
+    }
+    #[test]
+    fn behavior_wanderer() {
+        assert_eq!(decide(&Wanderer, Point::new(0, -1)), Intent::AttackPlayer);
+
+        let (enemy, _) = enemy_at(Point::new(5, 5));
+        match decide(&Wanderer, Point::new(5, 5)) {
+            Intent::MoveTo(position) => {
+                assert!(enemy.position.iter_neighbors().any(|p| p == position))
+            }
+            intent => panic!("{:?}", intent),
+        }
+    }
+    #[test]
+    fn behavior_static() {
+        assert_eq!(decide(&Static, Point::new(-1, 0)), Intent::AttackPlayer);
+        assert_eq!(decide(&Static, Point::new(-2, 0)), Intent::Wait);
+
+        // Doors can't be attacked through diagonally
+        let (enemy, mut context) = enemy_at(Point::new(1, 1));
+        context.level.tiles[enemy.position] = Tile::Door(DoorState::Open);
+        let world = world(&context);
+        assert_eq!(
+            Static.decide(&enemy, &world, &mut GameRng::seed_from_u64(0)),
+            Intent::Wait
+        );
+    }
+    #[test]
+    fn behavior_opens_doors() {
+        for (name, opens_doors) in [("Gnoll", true), ("Zombie", false)] {
+            let (enemy, mut context) = monster_at(name, Point::new(3, 0));
+            let door = enemy.position + Point::new(-1, 0);
+            let diagonal_door = enemy.position + Point::new(-1, 1);
+            context.level.tiles[door] = Tile::Door(DoorState::Closed);
+            context.level.tiles[diagonal_door] = Tile::Door(DoorState::Closed);
+            let world = world(&context);
+
+            assert_eq!(world.can_move(&enemy, enemy.position, door), opens_doors);
+            assert!(!world.can_move(&enemy, enemy.position, diagonal_door));
+        }
+    }
+    #[test]
+    fn behavior_ranged() {
+        let decide = |enemy: &Being, context: &GameContext| {
+            let world = world(context);
+            Ranged.decide(enemy, &world, &mut GameRng::seed_from_u64(0))
+        };
+
+        let (enemy, context) = monster_at("Kobold Archer", Point::new(5, 0));
+        assert_eq!(decide(&enemy, &context), Intent::ShootPlayer);
+
+        // Out of range, it comes closer
+        let (enemy, context) = monster_at("Kobold Archer", Point::new(9, 0));
+        assert_eq!(
+            decide(&enemy, &context),
+            Intent::MoveTo(enemy.position + Point::new(-1, 0))
+        );
+
+        // Too close, it backs off
+        let (enemy, context) = monster_at("Kobold Archer", Point::new(1, 0));
+        match decide(&enemy, &context) {
+            Intent::MoveTo(position) => {
+                assert!(position.distance_to(context.player.position) > 1)
+            }
+            intent => panic!("{:?}", intent),
+        }
+
+        // It doesn't shoot through other enemies
+        let (enemy, mut context) = monster_at("Kobold Archer", Point::new(5, 0));
+        let mut blocker = enemy.clone();
+        blocker.position = context.player.position + Point::new(2, 0);
+        context.level.enemies.push(blocker);
+        assert_ne!(decide(&enemy, &context), Intent::ShootPlayer);
+    }
+    #[test]
+    fn behavior_fleeing() {
+        let (enemy, context) = enemy_at(Point::new(1, 0));
+        match decide(&Fleeing, Point::new(1, 0)) {
+            Intent::MoveTo(position) => {
+                assert!(position.distance_to(context.player.position) > 1)
+            }
+            intent => panic!("{:?}", intent),
+        }
+
+        // Cornered, it fights back
+        let (_, mut context) = enemy_at(Point::new(1, 0));
+        for neighbor in enemy.position.iter_neighbors() {
+            if neighbor != context.player.position {
+                context.level.tiles[neighbor] = Tile::Wall;
+            }
+        }
+        let world = world(&context);
+        assert_eq!(
+            Fleeing.decide(&enemy, &world, &mut GameRng::seed_from_u64(0)),
+            Intent::AttackPlayer
+        );
+    }
+}
@@ -1,18 +1,79 @@
-use std::cmp::max;
+use std::cmp::{max, min};
 
-use rand::{thread_rng, Rng};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::{beingkind::BeingKind, point::Point};
+use crate::{
+    beingkind::BeingKind,
+    gear::{Equipment, GearSlot},
+    pickupitem::PickUpItem,
+    point::Point,
+    status::{StatusKind, Statuses},
+    ACTION_COST, LEVEL_UP_HP, MAX_NUTRITION, SEARCH_DURATION,
+};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Being {
     pub position: Point,
     pub max_health_points: i32,
     pub health_points: i32,
     pub experience_points: i32,
+    // The character level the experience points got the being to
+    pub experience_level: i32,
     pub max_attack: i32,
     pub max_shield: i32,
     pub kind: BeingKind,
+    pub inventory: Vec<PickUpItem>,
+    // Only the player gets hungry
+    pub nutrition: i32,
+    // max_attack and max_shield are the base stats, the rolls of the equipped gear add to them
+    pub equipment: Equipment,
+    // For how many more turns the being will run away from the player
+    pub fear_turns: u32,
+    // How much energy the being gains every tick, doubled by haste and halved by slowness
+    pub speed: i32,
+    // The being acts whenever it has at least ACTION_COST energy
+    pub energy: i32,
+    // What a hunting monster remembers about the player since it lost sight of them
+    pub memory: Option<Memory>,
+    // Whether the being's morale is broken by its wounds, making it run away until it recovers
+    pub fleeing: bool,
+    pub pack: Option<PackMember>,
+    pub statuses: Statuses,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PackRole {
+    Leader,
+    Follower,
+}
+
+// Monsters spawned together in a pack hunt together, sharing what they know about the player.
+// The followers stay around their leader until then.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackMember {
+    // Identifies the pack on its level
+    pub pack: u32,
+    // Identifies the being in its pack, wherever its mates stand
+    pub member: u32,
+    pub role: PackRole,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Memory {
+    pub player_position: Point,
+    // Once there, it searches around for this many turns before giving up
+    pub search_turns: u32,
+    pub searching: bool,
+}
+impl Memory {
+    pub fn new(player_position: Point) -> Self {
+        Memory {
+            player_position,
+            search_turns: SEARCH_DURATION,
+            searching: false,
+        }
+    }
 }
 impl Being {
     pub fn new(
@@ -28,59 +89,117 @@ impl Being {
             max_health_points: hp,
             health_points: hp,
             experience_points,
+            experience_level: 1,
             max_attack,
             max_shield,
             kind,
+            inventory: vec![],
+            nutrition: MAX_NUTRITION,
+            equipment: Equipment::default(),
+            fear_turns: 0,
+            speed: kind.speed(),
+            energy: 0,
+            memory: None,
+            fleeing: false,
+            pack: None,
+            statuses: Statuses::default(),
         }
     }
-    pub fn new_of_kind(kind: BeingKind, position: Point) -> Self {
-        let mut rng = thread_rng();
-
-        match kind {
-            BeingKind::Player => Being::new(position, 20, 0, 5, 1, kind),
-
-            BeingKind::Gnoll => Being::new(position, 9, rng.gen_range(10, 15), 4, 2, kind),
-            BeingKind::Bat => Being::new(position, 10, rng.gen_range(15, 20), 2, 3, kind),
-            BeingKind::AnimatedStatue => {
-                Being::new(position, 15, rng.gen_range(15, 20), 3, 1, kind)
-            }
-
-            BeingKind::Kestrel => Being::new(position, 10, rng.gen_range(20, 25), 5, 5, kind),
-            BeingKind::Emu => Being::new(position, 12, rng.gen_range(25, 30), 3, 3, kind),
-            BeingKind::LazyImp => Being::new(position, 20, rng.gen_range(25, 30), 5, 1, kind),
-
-            BeingKind::Troll => Being::new(position, 15, rng.gen_range(30, 35), 7, 5, kind),
-            BeingKind::Zombie => Being::new(position, 15, rng.gen_range(35, 40), 9, 4, kind),
-            BeingKind::StoneSatan => Being::new(position, 30, rng.gen_range(35, 40), 15, 5, kind),
+    pub fn new_of_kind(kind: BeingKind, position: Point, rng: &mut impl Rng) -> Self {
+        match kind.monster() {
+            None => Being {
+                position,
+                ..Being::new_player()
+            },
+            Some(monster) => Being::new(
+                position,
+                monster.hp,
+                rng.gen_range(monster.xp.0, monster.xp.1 + 1),
+                monster.attack,
+                monster.shield,
+                kind,
+            ),
         }
     }
     pub fn new_player() -> Self {
-        Being::new_of_kind(
-            BeingKind::Player,
-            Point::new(0, 0), // dummy position, will be later set to the one of the up_stairs
-        )
+        Being {
+            // The player gets to make the first move
+            energy: ACTION_COST,
+            ..Being::new(
+                Point::new(0, 0), // dummy position, will be later set to the one of the up_stairs
+                20,
+                0,
+                5,
+                1,
+                BeingKind::Player,
+            )
+        }
     }
-    pub fn fight(&mut self, other: &mut Being) -> i32 {
-        let mut rng = thread_rng();
-
-        let damage_dealt = max(
-            0,
-            rng.gen_range(0, self.max_attack + 1) - rng.gen_range(0, other.max_shield),
-        );
+    pub fn fight(&mut self, other: &mut Being, rng: &mut impl Rng) -> i32 {
+        let attack =
+            rng.gen_range(0, self.max_attack + 1) + self.equipment.roll(GearSlot::Weapon, rng);
+        let shield =
+            rng.gen_range(0, other.max_shield) + other.equipment.roll(GearSlot::Armour, rng);
+        let damage_dealt = max(0, attack - shield);
         other.health_points -= damage_dealt;
 
         damage_dealt
     }
+    // Every level-up raises the max HP, and either the attack or the shield, taking turns
+    pub fn level_up(&mut self) {
+        self.experience_level += 1;
+        self.max_health_points += LEVEL_UP_HP;
+        self.health_points += LEVEL_UP_HP;
+        if self.experience_level % 2 == 0 {
+            self.max_attack += 1;
+        } else {
+            self.max_shield += 1;
+        }
+    }
+    pub fn current_speed(&self) -> i32 {
+        if self.statuses.has(StatusKind::Haste) {
+            self.speed * 2
+        } else if self.statuses.has(StatusKind::Slow) {
+            self.speed / 2
+        } else {
+            self.speed
+        }
+    }
+    pub fn is_pack_mate_of(&self, other: &Being) -> bool {
+        match (self.pack, other.pack) {
+            (Some(pack), Some(other_pack)) => {
+                pack.pack == other_pack.pack && pack.member != other_pack.member
+            }
+            _ => false,
+        }
+    }
     pub fn bump_health(&mut self) {
         if self.health_points < self.max_health_points {
             self.health_points += 1;
         }
     }
+    pub fn heal(&mut self, amount: i32) -> i32 {
+        let healed = min(amount, self.max_health_points - self.health_points);
+        self.health_points += healed;
+
+        healed
+    }
+}
+
+// The XP needed to reach a character level: 20 for level 2, 60 for level 3, 120 for level 4...
+pub fn experience_for_level(level: i32) -> i32 {
+    10 * level * (level - 1)
 }
 
 #[cfg(test)]
 mod tests {
+    use rand::SeedableRng;
+
     use super::*;
+    use crate::{
+        gear::{Gear, GearKind},
+        GameRng,
+    };
 
     #[test]
     fn being_new_player() {
@@ -91,17 +210,81 @@ mod tests {
     }
     #[test]
     fn being_fight() {
+        let mut rng = GameRng::seed_from_u64(0);
         let mut player = Being::new_player();
-        let mut enemy = Being::new_of_kind(BeingKind::Bat, Point::new(0, 1));
+        let mut enemy =
+            Being::new_of_kind(BeingKind::named("Bat").unwrap(), Point::new(0, 1), &mut rng);
 
-        let damage_dealt = player.fight(&mut enemy);
+        let damage_dealt = player.fight(&mut enemy, &mut rng);
 
         assert_eq!(player.health_points, player.max_health_points);
         assert_eq!(enemy.health_points + damage_dealt, enemy.max_health_points);
     }
     #[test]
+    fn being_fight_with_gear() {
+        let mut rng = GameRng::seed_from_u64(0);
+        let mut player = Being::new_player();
+        player.max_attack = 0;
+        player.equipment.equip(Gear::new(GearKind::Axe, 2));
+        let mut enemy =
+            Being::new_of_kind(BeingKind::named("Bat").unwrap(), Point::new(0, 1), &mut rng);
+        enemy.max_shield = 1;
+
+        // With a max shield of 1 and no armour, the enemy can't shield itself at all
+        let damage_dealt = player.fight(&mut enemy, &mut rng);
+        assert!((4..=14).contains(&damage_dealt));
+    }
+    #[test]
+    fn being_heal() {
+        let mut player = Being::new_player();
+        player.health_points = 5;
+
+        assert_eq!(player.heal(3), 3);
+        assert_eq!(player.health_points, 8);
+        assert_eq!(player.heal(100), player.max_health_points - 8);
+        assert_eq!(player.health_points, player.max_health_points);
+    }
+    #[test]
+    fn being_level_up() {
+        assert_eq!(experience_for_level(1), 0);
+        assert_eq!(experience_for_level(2), 20);
+        assert_eq!(experience_for_level(4), 120);
+
+        let mut player = Being::new_player();
+        let (hp, attack, shield) = (
+            player.max_health_points,
+            player.max_attack,
+            player.max_shield,
+        );
+        player.level_up();
+        player.level_up();
+        assert_eq!(player.experience_level, 3);
+        assert_eq!(player.max_health_points, hp + 2 * LEVEL_UP_HP);
+        assert_eq!(player.health_points, player.max_health_points);
+        assert_eq!(
+            (player.max_attack, player.max_shield),
+            (attack + 1, shield + 1)
+        );
+    }
+    #[test]
+    fn being_current_speed() {
+        let mut player = Being::new_player();
+        player.statuses.add(StatusKind::Haste, 3);
+        assert_eq!(player.current_speed(), player.speed * 2);
+
+        // Slowness cancels the haste out
+        player.statuses.add(StatusKind::Slow, 3);
+        assert_eq!(player.current_speed(), player.speed);
+        player.statuses.add(StatusKind::Slow, 3);
+        assert_eq!(player.current_speed(), player.speed / 2);
+    }
+    #[test]
     fn being_bump_health() {
-        let mut enemy = Being::new_of_kind(BeingKind::Bat, Point::new(1, 1));
+        let mut enemy = Being::new_of_kind(
+            BeingKind::named("Bat").unwrap(),
+            Point::new(1, 1),
+            &mut GameRng::seed_from_u64(0),
+        );
 
         enemy.bump_health();
         assert_eq!(enemy.health_points, enemy.max_health_points);
@@ -110,4 +293,33 @@ mod tests {
         enemy.bump_health();
         assert_eq!(enemy.health_points, 2);
     }
+    #[test]
+    fn being_is_pack_mate_of() {
+        let mut bat = Being::new_of_kind(
+            BeingKind::named("Bat").unwrap(),
+            Point::new(1, 1),
+            &mut GameRng::seed_from_u64(0),
+        );
+        bat.pack = Some(PackMember {
+            pack: 0,
+            member: 0,
+            role: PackRole::Leader,
+        });
+        let mut mate = bat.clone();
+        mate.pack = Some(PackMember {
+            pack: 0,
+            member: 1,
+            role: PackRole::Follower,
+        });
+
+        // Being on the same tile doesn't make them any less mates, nor a being its own mate
+        assert!(bat.is_pack_mate_of(&mate));
+        assert!(!bat.is_pack_mate_of(&bat.clone()));
+        mate.pack = Some(PackMember {
+            pack: 1,
+            ..mate.pack.unwrap()
+        });
+        assert!(!bat.is_pack_mate_of(&mate));
+        assert!(!bat.is_pack_mate_of(&Being::new_player()));
+    }
 }
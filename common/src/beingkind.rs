@@ -1,42 +1,99 @@
 use std::fmt::Display;
 
-#[derive(Clone, Copy, Debug)]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    behavior::Behavior,
+    monsterkind::{monster_kinds, AiKind, Colour, MonsterKind},
+    NORMAL_SPEED,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BeingKind {
     Player,
-    AnimatedStatue,
-    Bat,
-    Emu,
-    Gnoll,
-    Kestrel,
-    LazyImp,
-    StoneSatan,
-    Troll,
-    Zombie,
+    // Monster(index of its definition in monster_kinds())
+    // It's saved by name, so that saves survive changes to the order of the definitions.
+    Monster(#[serde(with = "monster_by_name")] usize),
+}
+impl BeingKind {
+    pub fn named(name: &str) -> Option<Self> {
+        monster_kinds()
+            .iter()
+            .position(|kind| kind.name == name)
+            .map(BeingKind::Monster)
+    }
+    pub fn monster(self) -> Option<&'static MonsterKind> {
+        match self {
+            BeingKind::Player => None,
+            BeingKind::Monster(index) => Some(&monster_kinds()[index]),
+        }
+    }
+    pub fn glyph(self) -> char {
+        self.monster().map_or('@', |monster| monster.glyph)
+    }
+    pub fn colour(self) -> Colour {
+        self.monster()
+            .map_or(Colour::Green, |monster| monster.colour)
+    }
+    pub fn ai(self) -> Option<AiKind> {
+        self.monster().map(|monster| monster.ai)
+    }
+    // What decides the being's actions; the player decides their own
+    pub fn behavior(self) -> Option<&'static dyn Behavior> {
+        self.ai().map(AiKind::behavior)
+    }
+    pub fn speed(self) -> i32 {
+        self.monster().map_or(NORMAL_SPEED, |monster| monster.speed)
+    }
 }
 impl Display for BeingKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                BeingKind::Player => "Player",
-                BeingKind::AnimatedStatue => "Animated Statue",
-                BeingKind::Bat => "Bat",
-                BeingKind::LazyImp => "Lazy Imp",
-                BeingKind::Emu => "Emu",
-                BeingKind::Gnoll => "Gnoll",
-                BeingKind::Kestrel => "Kestrel",
-                BeingKind::StoneSatan => "Stone Satan",
-                BeingKind::Troll => "Troll",
-                BeingKind::Zombie => "Zombie",
-            }
-        )
+        match self.monster() {
+            Some(monster) => write!(f, "{}", monster.name),
+            None => write!(f, "Player"),
+        }
     }
 }
 
-#[rustfmt::skip]
-pub const ENEMIES_KIND: [BeingKind; 9] = [
-    BeingKind::Gnoll,   BeingKind::Bat,    BeingKind::AnimatedStatue,
-    BeingKind::Kestrel, BeingKind::Emu,    BeingKind::LazyImp,
-    BeingKind::Troll,   BeingKind::Zombie, BeingKind::StoneSatan,
-];
+mod monster_by_name {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::monsterkind::monster_kinds;
+
+    pub fn serialize<S: Serializer>(index: &usize, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&monster_kinds()[*index].name)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        monster_kinds()
+            .iter()
+            .position(|kind| kind.name == name)
+            .ok_or_else(|| D::Error::custom(format!("unknown monster {}", name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn beingkind_named() {
+        let kestrel = BeingKind::named("Kestrel").unwrap();
+        assert_eq!(kestrel.to_string(), "Kestrel");
+        assert_eq!(kestrel.glyph(), 'K');
+        assert_eq!(kestrel.speed(), NORMAL_SPEED * 2);
+        assert_eq!(kestrel.ai(), Some(AiKind::Chaser));
+
+        assert_eq!(BeingKind::named("Dragon"), None);
+        assert_eq!(BeingKind::Player.to_string(), "Player");
+        assert_eq!(BeingKind::Player.ai(), None);
+    }
+    #[test]
+    fn beingkind_saved_by_name() {
+        let zombie = BeingKind::named("Zombie").unwrap();
+        let saved = ron::to_string(&zombie).unwrap();
+        assert_eq!(saved, "Monster(\"Zombie\")");
+        assert_eq!(ron::from_str::<BeingKind>(&saved).unwrap(), zombie);
+        assert!(ron::from_str::<BeingKind>("Monster(\"Dragon\")").is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use serde::Serialize;
+
+// Game data embedded in the game, which a file or directory next to the game can replace.
+// Replacing it has to happen before any game is started, as games rely on it not changing.
+pub struct DataFile<T: 'static> {
+    // Where the replacement is looked for
+    pub override_path: &'static str,
+    default: fn() -> T,
+    read: fn(&Path) -> Result<T, DataError>,
+    data: OnceLock<T>,
+}
+impl<T> DataFile<T> {
+    pub const fn new(
+        override_path: &'static str,
+        default: fn() -> T,
+        read: fn(&Path) -> Result<T, DataError>,
+    ) -> Self {
+        DataFile {
+            override_path,
+            default,
+            read,
+            data: OnceLock::new(),
+        }
+    }
+    // The data in use; the default one, unless another was loaded first
+    pub fn get(&self) -> &T {
+        self.data.get_or_init(self.default)
+    }
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<(), LoadError> {
+        let path = path.as_ref();
+        let error = |error| LoadError {
+            path: path.to_path_buf(),
+            error,
+        };
+        let data = (self.read)(path).map_err(error)?;
+        self.data
+            .set(data)
+            .map_err(|_| error(DataError::AlreadyLoaded))
+    }
+    // Loads the replacement if there's one
+    pub fn load_override(&self) -> Result<(), LoadError> {
+        if Path::new(self.override_path).exists() {
+            self.load(self.override_path)
+        } else {
+            Ok(())
+        }
+    }
+}
+impl<T: Serialize> DataFile<T> {
+    // A hash of the data in use, written as RON, which tells apart games played with different data.
+    // It's worked out by hand (FNV-1a) since the standard hasher may change between Rust versions.
+    pub fn fingerprint(&self) -> u64 {
+        ron::to_string(self.get())
+            .expect("the game data can always be written as RON")
+            .bytes()
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+}
+
+#[derive(Debug)]
+pub enum DataError {
+    Io(io::Error),
+    Format(ron::Error),
+    // Invalid(what's wrong, starting with what it's wrong with)
+    Invalid(String),
+    AlreadyLoaded,
+}
+impl Display for DataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataError::Io(error) => write!(f, "couldn't read it: {}", error),
+            DataError::Format(error) => write!(f, "it's malformed: {}", error),
+            DataError::Invalid(problem) => write!(f, "invalid {}", problem),
+            DataError::AlreadyLoaded => write!(f, "it has to be loaded before the game starts"),
+        }
+    }
+}
+impl Error for DataError {}
+impl From<io::Error> for DataError {
+    fn from(error: io::Error) -> Self {
+        DataError::Io(error)
+    }
+}
+impl From<ron::error::SpannedError> for DataError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        DataError::Format(error.code)
+    }
+}
+
+// The data at the path couldn't replace the default one
+#[derive(Debug)]
+pub struct LoadError {
+    pub path: PathBuf,
+    pub error: DataError,
+}
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Couldn't load {}: {}", self.path.display(), self.error)
+    }
+}
+impl Error for LoadError {}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn read(path: &Path) -> Result<String, DataError> {
+        Ok(fs::read_to_string(path)?)
+    }
+
+    #[test]
+    fn datafile_load() {
+        let data = DataFile::new("missing.txt", || "default".to_string(), read);
+        assert!(data.load_override().is_ok());
+        assert!(matches!(
+            data.load("missing.txt"),
+            Err(LoadError {
+                error: DataError::Io(_),
+                ..
+            })
+        ));
+        assert_eq!(data.get(), "default");
+
+        // Once it's in use, it can't be replaced anymore
+        assert!(matches!(
+            data.load("Cargo.toml"),
+            Err(LoadError {
+                error: DataError::AlreadyLoaded,
+                ..
+            })
+        ));
+        assert_eq!(data.get(), "default");
+    }
+    #[test]
+    fn datafile_fingerprint() {
+        let data = DataFile::new("missing.txt", || "default".to_string(), read);
+        let other = DataFile::new("missing.txt", || "other".to_string(), read);
+
+        assert_eq!(data.fingerprint(), data.fingerprint());
+        assert_ne!(data.fingerprint(), other.fingerprint());
+    }
+}
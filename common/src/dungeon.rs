@@ -0,0 +1,201 @@
+use std::{fs, sync::OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    datafile::{DataError, DataFile},
+    levelgen::GeneratorKind,
+};
+
+// The dungeon configuration the game ships with
+const DEFAULT_DUNGEON: &str = include_str!("../data/dungeon.ron");
+// If a file with this name is found next to the game, it replaces the default configuration
+pub const DUNGEON_FILE_NAME: &str = "dungeon.ron";
+
+static DEFAULT_CONFIG: OnceLock<DungeonConfig> = OnceLock::new();
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DungeonConfig {
+    #[serde(default = "default_level_count")]
+    pub level_count: i32,
+    #[serde(default = "default_dimensions")]
+    pub dimensions: Dimensions,
+    pub generators: Vec<GeneratorRange>,
+    #[serde(default)]
+    pub vault_chances: Vec<VaultChance>,
+}
+// The configurations that leave out the level count or the dimensions get the default ones
+fn default_level_count() -> i32 {
+    default_config().level_count
+}
+fn default_dimensions() -> Dimensions {
+    default_config().dimensions
+}
+// The size of every level, and how wide or tall the rooms carved in them can get,
+// from min_room_width up to just under max_room_width
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dimensions {
+    pub width: i32,
+    pub height: i32,
+    pub min_room_width: i32,
+    pub max_room_width: i32,
+}
+// The generator building the levels between the two depths, both included
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GeneratorRange {
+    pub depth: (i32, i32),
+    pub generator: GeneratorKind,
+}
+// The percent chance of a level between the two depths, both included, to hold a vault
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct VaultChance {
+    pub depth: (i32, i32),
+    pub chance: u32,
+}
+impl DungeonConfig {
+    pub fn generator_for(&self, level: i32) -> GeneratorKind {
+        self.generators
+            .iter()
+            .find(|range| (range.depth.0..=range.depth.1).contains(&level))
+            .map_or(GeneratorKind::RoomsAndCorridors, |range| range.generator)
+    }
+    pub fn vault_chance(&self, level: i32) -> u32 {
+        self.vault_chances
+            .iter()
+            .find(|range| (range.depth.0..=range.depth.1).contains(&level))
+            .map_or(0, |range| range.chance)
+    }
+    fn validate(&self) -> Result<(), DataError> {
+        let dimensions = self.dimensions;
+        if self.level_count < 1 {
+            return Err(DataError::Invalid(
+                "dungeon: it needs at least one level".to_string(),
+            ));
+        }
+        if dimensions.min_room_width < 3 || dimensions.min_room_width >= dimensions.max_room_width {
+            return Err(DataError::Invalid(format!(
+                "dungeon: the rooms can't be from {} to {} tiles wide",
+                dimensions.min_room_width, dimensions.max_room_width
+            )));
+        }
+        // The levels have to fit two of the biggest rooms side by side either way
+        if dimensions.width < 2 * dimensions.max_room_width
+            || dimensions.height < 2 * dimensions.max_room_width
+        {
+            return Err(DataError::Invalid(format!(
+                "dungeon: a {}x{} level is too small for rooms up to {} tiles wide",
+                dimensions.width, dimensions.height, dimensions.max_room_width
+            )));
+        }
+        if let Some(range) = self
+            .generators
+            .iter()
+            .find(|range| range.depth.0 > range.depth.1)
+        {
+            return Err(DataError::Invalid(format!(
+                "dungeon: the depth range of the {:?} generator is empty",
+                range.generator
+            )));
+        }
+        if let Some(level) = (1..=self.level_count).find(|&level| {
+            self.generators
+                .iter()
+                .all(|range| !(range.depth.0..=range.depth.1).contains(&level))
+        }) {
+            return Err(DataError::Invalid(format!(
+                "dungeon: no generator builds level {}",
+                level
+            )));
+        }
+        if let Some(range) = self
+            .vault_chances
+            .iter()
+            .find(|range| range.depth.0 > range.depth.1 || range.chance > 100)
+        {
+            return Err(DataError::Invalid(format!(
+                "dungeon: the vault chance for the depths {} to {} isn't a percent of a non-empty range",
+                range.depth.0, range.depth.1
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+pub fn parse(source: &str) -> Result<DungeonConfig, DataError> {
+    let config: DungeonConfig = ron::from_str(source)?;
+    config.validate()?;
+
+    Ok(config)
+}
+fn default_config() -> &'static DungeonConfig {
+    DEFAULT_CONFIG.get_or_init(|| parse(DEFAULT_DUNGEON).unwrap())
+}
+pub static DUNGEON_CONFIG: DataFile<DungeonConfig> = DataFile::new(
+    DUNGEON_FILE_NAME,
+    || default_config().clone(),
+    |path| parse(&fs::read_to_string(path)?),
+);
+pub fn dungeon_config() -> &'static DungeonConfig {
+    DUNGEON_CONFIG.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dungeon_default_config() {
+        let config = parse(DEFAULT_DUNGEON).unwrap();
+        assert_eq!(config.generator_for(1), GeneratorKind::RoomsAndCorridors);
+        assert_eq!(config.level_count, 25);
+        assert_eq!(
+            config.generator_for(config.level_count),
+            GeneratorKind::Caves
+        );
+        assert!(config.vault_chance(1) > 0);
+        assert_eq!(config.vault_chance(config.level_count + 1), 0);
+    }
+    #[test]
+    fn dungeon_invalid() {
+        let config = "(generators: [(depth: (1, 25), generator: Bsp)])";
+
+        let parsed = parse(config).unwrap();
+        assert_eq!(parsed.level_count, default_config().level_count);
+        assert_eq!(parsed.dimensions, default_config().dimensions);
+        assert!(matches!(
+            parse(&config.replace("25", "24")),
+            Err(DataError::Invalid(_))
+        ));
+        assert!(matches!(
+            parse(&config.replace("(1, 25)", "(25, 1)")),
+            Err(DataError::Invalid(_))
+        ));
+        assert!(matches!(
+            parse(&config.replace("Bsp", "Maze")),
+            Err(DataError::Format(_))
+        ));
+
+        let tiny = "(level_count: 3, dimensions: (width: 20, height: 12, min_room_width: 3, max_room_width: 6), generators: [(depth: (1, 3), generator: Caves)])";
+        assert!(parse(tiny).is_ok());
+        assert!(matches!(
+            parse(&tiny.replace("height: 12", "height: 10")),
+            Err(DataError::Invalid(_))
+        ));
+        assert!(matches!(
+            parse(&tiny.replace("max_room_width: 6", "max_room_width: 3")),
+            Err(DataError::Invalid(_))
+        ));
+        assert!(matches!(
+            parse(&tiny.replace("level_count: 3", "level_count: 4")),
+            Err(DataError::Invalid(_))
+        ));
+
+        let chances = "(generators: [(depth: (1, 25), generator: Bsp)], vault_chances: [(depth: (1, 25), chance: 50)])";
+        assert_eq!(parse(chances).unwrap().vault_chance(7), 50);
+        assert!(matches!(
+            parse(&chances.replace("50", "150")),
+            Err(DataError::Invalid(_))
+        ));
+    }
+}
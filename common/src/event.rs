@@ -1,14 +1,63 @@
-use crate::{beingkind::BeingKind, pickupitem::PickUpItem, LEVEL_COUNT};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug)]
+use crate::{
+    beingkind::BeingKind,
+    dungeon::dungeon_config,
+    gear::{Gear, GearSlot},
+    hunger::Hunger,
+    pickupitem::PickUpItem,
+    status::StatusKind,
+    trap::TrapKind,
+};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Event {
     Killed(BeingKind, i32 /* xp gained */),
     Attacked(BeingKind, i32 /* damage dealt */),
     GotAttacked(BeingKind, i32 /*damage dealt */),
+    GotShot(BeingKind, i32 /* damage dealt */),
     // OnItem = player is on a Item or a downstairs stair.
     // Used to show a message informing the player that they can press enter to descend/pick up.
     OnItem(Option<PickUpItem>),
     OnUpstairs,
+    PickedUp(PickUpItem),
+    InventoryFull,
+    UsedItem(PickUpItem),
+    DroppedItem(PickUpItem),
+    CantDropHere,
+    ThrewItem(PickUpItem),
+    CantThrowThere,
+    HitWithThrow(BeingKind, i32 /* damage dealt */),
+    Healed(i32 /* hp gained */),
+    Blinked,
+    NowhereToBlink,
+    Teleported,
+    MagicMapped,
+    Frightened(i32 /* enemies frightened */),
+    Equipped(Gear),
+    Unequipped(Gear),
+    NothingEquipped(GearSlot),
+    GotStatus(StatusKind),
+    StatusWoreOff(StatusKind),
+    Stunned,
+    Ate(PickUpItem),
+    TriggeredTrap(TrapKind),
+    ThrowSetOffTrap(TrapKind),
+    HitByDart(i32 /* damage dealt */),
+    FoundTrap(TrapKind),
+    FoundSecretDoor,
+    FoundNothing,
+    OpenedDoor,
+    UnlockedDoor,
+    DoorLocked,
+    ClosedDoor,
+    DoorBlocked,
+    NoDoorThere,
+    KeyUse,
+    HungerChanged(Hunger),
+    LostTrail(BeingKind),
+    Fled(BeingKind),
+    LeveledUp(i32 /* new experience level */),
     Died(i32 /* xp on death */),
     Won(i32 /* xp on win */),
 }
@@ -27,11 +76,17 @@ impl Event {
                     enemy_kind, damage
                 )
             }
+            Event::GotShot(enemy_kind, damage) => {
+                format!(
+                    "You got hit from afar by {}, taking {} damage.\n",
+                    enemy_kind, damage
+                )
+            }
             Event::OnItem(Some(item)) => {
-                format!("Press Enter to apply the {}\n", item)
+                format!("Press Enter to pick up the {}\n", item)
             }
             Event::OnItem(None) => {
-                if level < LEVEL_COUNT {
+                if level < dungeon_config().level_count {
                     format!(
                         "Press Enter to descend to level {}. You can go back later on.\n",
                         level + 1
@@ -42,11 +97,89 @@ impl Event {
                 }
             }
             Event::OnUpstairs => {
+                format!("Press Enter to ascend to level {}.\n", level - 1)
+            }
+            Event::PickedUp(item) => format!("You picked up the {}.\n", item),
+            Event::InventoryFull => "Your inventory is full.\n".to_string(),
+            Event::UsedItem(item) => format!("You used the {}.\n", item),
+            Event::DroppedItem(item) => format!("You dropped the {}.\n", item),
+            Event::CantDropHere => "There's no room to drop anything here.\n".to_string(),
+            Event::ThrewItem(item) => format!("You threw the {}.\n", item),
+            Event::CantThrowThere => "There's no room to throw anything there.\n".to_string(),
+            Event::HitWithThrow(enemy_kind, damage) => {
+                format!(
+                    "Your throw hit {}, dealing {} damage.\n",
+                    enemy_kind, damage
+                )
+            }
+            Event::Healed(hp) => format!("You drank the potion, healing {} HP.\n", hp),
+            Event::Blinked => "You blinked to a nearby spot.\n".to_string(),
+            Event::NowhereToBlink => {
+                "You drank the potion, but there was nowhere to blink to.\n".to_string()
+            }
+            Event::Teleported => "You got teleported somewhere else on this level.\n".to_string(),
+            Event::MagicMapped => "The layout of this level is revealed to you.\n".to_string(),
+            Event::Frightened(0) => {
+                "You read the scroll, but nobody was there to scare.\n".to_string()
+            }
+            Event::Frightened(count) => format!("You frightened {} enemies away.\n", count),
+            Event::Equipped(gear) => format!("You equipped the {}.\n", gear),
+            Event::Unequipped(gear) => format!("You took off the {}.\n", gear),
+            Event::NothingEquipped(GearSlot::Weapon) => {
+                "You aren't wielding a weapon.\n".to_string()
+            }
+            Event::NothingEquipped(GearSlot::Armour) => {
+                "You aren't wearing any armour.\n".to_string()
+            }
+            Event::GotStatus(status) => {
+                format!("You are {}!\n", status.to_string().to_lowercase())
+            }
+            Event::StatusWoreOff(status) => {
+                format!("You are no longer {}.\n", status.to_string().to_lowercase())
+            }
+            Event::Stunned => "You are stunned and can't act.\n".to_string(),
+            Event::TriggeredTrap(TrapKind::Dart) => {
+                "You stepped on a dart trap! A dart shoots out at you.\n".to_string()
+            }
+            Event::TriggeredTrap(TrapKind::Teleport) => {
+                "You stepped on a teleport trap!\n".to_string()
+            }
+            Event::TriggeredTrap(TrapKind::Alarm) => {
+                "You stepped on an alarm trap! Every monster around heard where you are.\n"
+                    .to_string()
+            }
+            Event::TriggeredTrap(TrapKind::Trapdoor) => {
                 format!(
-                    "Press Enter to ascend to level {}.\n",
-                    level - 1
+                    "A trapdoor opens under your feet! You fall to level {}.\n",
+                    level
                 )
             }
+            Event::ThrowSetOffTrap(trap_kind) => format!("Your throw set off a {}!\n", trap_kind),
+            Event::HitByDart(damage) => format!("The dart hits you, dealing {} damage.\n", damage),
+            Event::FoundTrap(trap_kind) => format!("You found a {}.\n", trap_kind),
+            Event::FoundSecretDoor => "You found a secret door!\n".to_string(),
+            Event::FoundNothing => "You searched around, but found nothing.\n".to_string(),
+            Event::OpenedDoor => "You opened the door.\n".to_string(),
+            Event::UnlockedDoor => "You unlocked the door with a key.\n".to_string(),
+            Event::DoorLocked => "This door is locked. You need a key to open it.\n".to_string(),
+            Event::ClosedDoor => "You closed the door.\n".to_string(),
+            Event::DoorBlocked => "Something is in the way of the door.\n".to_string(),
+            Event::NoDoorThere => "There's no door to do that with there.\n".to_string(),
+            Event::KeyUse => "Walk into a locked door to unlock it with the key.\n".to_string(),
+            Event::Ate(item) => format!("You ate the {}.\n", item),
+            Event::HungerChanged(Hunger::Fed) => "You are no longer hungry.\n".to_string(),
+            Event::HungerChanged(Hunger::Hungry) => "You are getting hungry.\n".to_string(),
+            Event::HungerChanged(Hunger::Weak) => {
+                "You feel weak from hunger, and your wounds stop healing.\n".to_string()
+            }
+            Event::HungerChanged(Hunger::Starving) => {
+                "You are starving! Eat something before it's too late!\n".to_string()
+            }
+            Event::LostTrail(enemy_kind) => format!("The {} lost your trail.\n", enemy_kind),
+            Event::Fled(enemy_kind) => format!("The {} turns to flee!\n", enemy_kind),
+            Event::LeveledUp(level) => {
+                format!("Welcome to level {}! You feel stronger.\n", level)
+            }
             Event::Died(xp) => format!("YOU LOST THIS GAME...\nYou died with {} XP.\n", xp),
             Event::Won(xp) => format!(
                 "YOU WON! THE AMULET OF YENDOR IS YOURS!\nYou won with {} XP.\n",
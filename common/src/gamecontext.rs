@@ -1,30 +1,84 @@
-use pathfinding::prelude::astar;
-use rand::{thread_rng, Rng};
+use std::{
+    cell::OnceCell,
+    cmp::{max, min, Reverse},
+    collections::BinaryHeap,
+};
+
+use rand::{thread_rng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 use symmetric_shadowcasting::{compute_fov, Pos};
 
 use crate::{
-    being::Being, beingkind::BeingKind, event::Event, level::Level, pickupitem::PickUpItem,
-    playeraction::PlayerAction, point::Point, tile::Tile, LEVEL_COUNT, LEVEL_HEIGHT, LEVEL_WIDTH,
+    behavior::{Fleeing, Intent, World},
+    being::{experience_for_level, Being, Memory},
+    beingkind::BeingKind,
+    dungeon::{dungeon_config, DungeonConfig},
+    event::Event,
+    gear::GearSlot,
+    hunger::Hunger,
+    level::Level,
+    pickupitem::PickUpItem,
+    playeraction::PlayerAction,
+    point::{Point, DIRECTIONS},
+    replay::Replay,
+    status::StatusKind,
+    tile::{DoorState, Tile},
+    tilearray::TileArray,
+    trap::{Trap, TrapKind},
+    GameRng, ACTION_COST, BLIND_SIGHT_RADIUS, BLINK_RANGE, FEAR_DURATION, FOOD_RATION_NUTRITION,
+    HASTE_DURATION, HEALING_POTION_HP, INVENTORY_SIZE, MAX_NUTRITION, PERCEPTION_CHANCE,
+    PLAYER_SEARCH_RADIUS, REGENERATION_DURATION, THROW_RANGE,
 };
 
-#[derive(Debug)]
+// Whoever's turn it is in the scheduler.
+// On equal energy, enemies act in the order of their index, and the player last.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Actor {
+    // Enemy(index in the level's array of enemies)
+    Enemy(usize),
+    Player,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct GameContext {
     levels: Vec<Option<Level>>,
     pub level: Level,
     pub current_level: i32,
     pub current_turn: u32,
+    // How many turns of world time went by, whatever the speed of the player
+    pub world_turn: u32,
     pub player: Being,
     pub events: Vec<Event>,
+    // The seed the game was started with. Starting a game with the same seed
+    // and feeding it the same actions will produce the same game.
+    pub seed: u64,
+    // Every action the player took, in order, so that the game can be replayed.
+    pub actions: Vec<PlayerAction>,
+    rng: GameRng,
+    // Where fleeing enemies would rather be, computed at most once per player turn
+    #[serde(skip)]
+    pub(crate) flee_map: OnceCell<TileArray<i32>>,
 }
 impl GameContext {
     pub fn new() -> Self {
+        GameContext::new_with_seed(thread_rng().gen())
+    }
+    pub fn new_with_seed(seed: u64) -> Self {
+        let mut rng = GameRng::seed_from_u64(seed);
+        let level = Level::generate(1, &mut rng);
+
         let mut context = GameContext {
-            levels: vec![None; LEVEL_COUNT as usize],
-            level: Level::generate(1),
+            levels: vec![None; dungeon_config().level_count as usize],
+            level,
             current_level: 1,
             current_turn: 1,
+            world_turn: 0,
             player: Being::new_player(),
             events: vec![],
+            seed,
+            actions: vec![],
+            rng,
+            flee_map: OnceCell::new(),
         };
 
         context.player.position = context.level.up_stairs;
@@ -33,59 +87,737 @@ impl GameContext {
         context
     }
     fn update_fov(&mut self) {
+        let player_position = self.player.position;
+        let is_blind = self.player.statuses.has(StatusKind::Blindness);
+        let (width, height) = (self.level.tiles.width(), self.level.tiles.height());
         compute_fov(
             self.player.position.into(),
             &mut |(y, x): Pos| {
                 self.level.tiles[(y as i32, x as i32)].is_blocking()
                     || y == 0
                     || x == 0
-                    || (y as i32) == LEVEL_HEIGHT
-                    || (x as i32) == LEVEL_WIDTH
+                    || (y as i32) == height
+                    || (x as i32) == width
             },
             &mut |(y, x): Pos| {
-                self.level.last_seen[(y as i32, x as i32)] = self.current_turn;
+                let point = Point::new(x as i32, y as i32);
+                if !is_blind || point.distance_to(player_position) <= BLIND_SIGHT_RADIUS {
+                    self.level.last_seen[point] = self.current_turn;
+                }
             },
         );
     }
-    pub fn next_turn(&mut self, action: PlayerAction) {
-        let mut rng = thread_rng();
+    pub fn replay(&self) -> Replay {
+        Replay::new(self.seed, self.actions.clone())
+    }
+    fn pick_up(&mut self) {
+        if let Tile::Item(item) = self.level.tiles[self.player.position] {
+            if self.player.inventory.len() < INVENTORY_SIZE {
+                self.player.inventory.push(item);
+                self.level.tiles[self.player.position] = Tile::Room;
+                self.events.push(Event::PickedUp(item));
+            } else {
+                self.events.push(Event::InventoryFull);
+            }
+        }
+    }
+    fn use_item(&mut self, item: PickUpItem) {
+        match item {
+            PickUpItem::HealthBoost => {
+                self.player.max_health_points += 1;
+                self.player.health_points = self.player.max_health_points;
+            }
+            // The boosts are permanent: they raise the base stats,
+            // which the gear's rolls add to whatever is equipped
+            PickUpItem::AttackBoost => {
+                self.player.max_attack += 1;
+            }
+            PickUpItem::ShieldBoost => {
+                self.player.max_shield += 1;
+            }
+            PickUpItem::Gear(gear) => {
+                if let Some(old_gear) = self.player.equipment.equip(gear) {
+                    self.player.inventory.push(PickUpItem::Gear(old_gear));
+                }
+                self.events.push(Event::Equipped(gear));
+                return;
+            }
+            PickUpItem::HealingPotion => {
+                let healed = self.player.heal(HEALING_POTION_HP);
+                self.events.push(Event::Healed(healed));
+                return;
+            }
+            PickUpItem::Key => unreachable!("keys never get taken out to be used, see next_turn"),
+            PickUpItem::FoodRation => {
+                let hunger = Hunger::of(self.player.nutrition);
+                self.player.nutrition =
+                    min(MAX_NUTRITION, self.player.nutrition + FOOD_RATION_NUTRITION);
+                self.events.push(Event::Ate(item));
+                if Hunger::of(self.player.nutrition) != hunger {
+                    self.events
+                        .push(Event::HungerChanged(Hunger::of(self.player.nutrition)));
+                }
+                return;
+            }
+            PickUpItem::SpeedPotion => {
+                self.player.statuses.add(StatusKind::Haste, HASTE_DURATION);
+                self.events.push(Event::GotStatus(StatusKind::Haste));
+                return;
+            }
+            PickUpItem::RegenerationPotion => {
+                self.player
+                    .statuses
+                    .add(StatusKind::Regeneration, REGENERATION_DURATION);
+                self.events.push(Event::GotStatus(StatusKind::Regeneration));
+                return;
+            }
+            PickUpItem::BlinkPotion => {
+                self.update_fov();
+                let destinations: Vec<Point> = self
+                    .level
+                    .tiles
+                    .points()
+                    .filter(|&p| {
+                        p.x.abs_diff(self.player.position.x) as i32 <= BLINK_RANGE
+                            && p.y.abs_diff(self.player.position.y) as i32 <= BLINK_RANGE
+                    })
+                    .filter(|&p| self.level.last_seen[p] == self.current_turn)
+                    .filter(|&p| self.is_free_floor(p))
+                    .collect();
+                // Boxed in, the potion is wasted all the same
+                if destinations.is_empty() {
+                    self.events.push(Event::NowhereToBlink);
+                } else {
+                    self.player.position = destinations[self.rng.gen_range(0, destinations.len())];
+                    self.events.push(Event::Blinked);
+                }
+                return;
+            }
+            PickUpItem::TeleportScroll => {
+                self.teleport_player();
+                return;
+            }
+            PickUpItem::MagicMappingScroll => {
+                // Walls deep in the rock have nothing to show, so only those bordering
+                // the walkable part of the level get revealed.
+                let (width, height) = (self.level.tiles.width(), self.level.tiles.height());
+                for y in 0..height {
+                    for x in 0..width {
+                        let point = Point::new(x, y);
+                        let is_interesting = point.iter_within(1).any(|p| {
+                            self.level.tiles.in_bounds(p) && self.level.tiles[p] != Tile::Wall
+                        });
+                        if is_interesting && self.level.last_seen[point] == 0 {
+                            self.level.last_seen[point] = 1;
+                        }
+                        if self.level.tiles[point] == Tile::Door(DoorState::Secret) {
+                            self.level.tiles[point] = Tile::Door(DoorState::Closed);
+                        }
+                    }
+                }
+                self.events.push(Event::MagicMapped);
+                return;
+            }
+            PickUpItem::FearScroll => {
+                self.update_fov();
+                let mut frightened = 0;
+                for enemy in self.level.enemies.iter_mut() {
+                    if self.level.last_seen[enemy.position] == self.current_turn {
+                        enemy.fear_turns = FEAR_DURATION;
+                        frightened += 1;
+                    }
+                }
+                self.events.push(Event::Frightened(frightened));
+                return;
+            }
+        }
+        self.events.push(Event::UsedItem(item));
+    }
+    // Opens the door at the point, using up a key if it's locked
+    fn open_door(&mut self, point: Point) {
+        if !self.level.tiles.in_bounds(point) {
+            self.events.push(Event::NoDoorThere);
+            return;
+        }
+        match self.level.tiles[point] {
+            Tile::Door(DoorState::Closed) => {
+                self.level.tiles[point] = Tile::Door(DoorState::Open);
+                self.events.push(Event::OpenedDoor);
+            }
+            Tile::Door(DoorState::Locked) => {
+                match self
+                    .player
+                    .inventory
+                    .iter()
+                    .position(|&item| item == PickUpItem::Key)
+                {
+                    Some(index) => {
+                        self.player.inventory.remove(index);
+                        self.level.tiles[point] = Tile::Door(DoorState::Open);
+                        self.events.push(Event::UnlockedDoor);
+                    }
+                    None => self.events.push(Event::DoorLocked),
+                }
+            }
+            _ => self.events.push(Event::NoDoorThere),
+        }
+    }
+    // Closes the open door at the point, unless someone stands in the doorway
+    fn close_door(&mut self, point: Point) {
+        if !self.level.tiles.in_bounds(point)
+            || self.level.tiles[point] != Tile::Door(DoorState::Open)
+        {
+            self.events.push(Event::NoDoorThere);
+        } else if point == self.player.position
+            || self
+                .level
+                .enemies
+                .iter()
+                .any(|enemy| enemy.position == point)
+        {
+            self.events.push(Event::DoorBlocked);
+        } else {
+            self.level.tiles[point] = Tile::Door(DoorState::Closed);
+            self.events.push(Event::ClosedDoor);
+        }
+    }
+    fn teleport_player(&mut self) {
+        self.player.position = self.random_free_floor();
+        self.events.push(Event::Teleported);
+    }
+    // A random floor tile nobody stands on, never the stairs
+    fn random_free_floor(&mut self) -> Point {
+        loop {
+            let proposed_position = self.level.tiles.generate_floor_point(&mut self.rng);
+            if self.is_free_floor(proposed_position) {
+                break proposed_position;
+            }
+        }
+    }
+    // Leaves the current level for the one at the given progression from it,
+    // generating it if it wasn't visited before
+    fn change_level(&mut self, progression: i32) {
+        self.current_level += progression;
+        self.levels[(self.current_level - progression - 1) as usize] = Some(self.level.clone());
+
+        self.level = self.levels[(self.current_level - 1) as usize]
+            .clone()
+            .unwrap_or_else(|| Level::generate(self.current_level, &mut self.rng));
+    }
+    fn trigger_trap(&mut self, trap: Trap) {
+        self.level.tiles[self.player.position] = Tile::Trap(Trap {
+            found: true,
+            ..trap
+        });
+        self.events.push(Event::TriggeredTrap(trap.kind));
+
+        match trap.kind {
+            TrapKind::Dart => {
+                let damage_dealt = self.rng.gen_range(1, 5);
+                self.player.health_points -= damage_dealt;
+                self.events.push(Event::HitByDart(damage_dealt));
+            }
+            TrapKind::Teleport => {
+                self.teleport_player();
+            }
+            TrapKind::Alarm => {
+                for enemy in self.level.enemies.iter_mut() {
+                    if enemy.pack.is_some()
+                        || enemy
+                            .kind
+                            .behavior()
+                            .is_some_and(|behavior| behavior.hunts())
+                    {
+                        enemy.memory = Some(Memory::new(self.player.position));
+                    }
+                }
+            }
+            TrapKind::Trapdoor => {
+                self.change_level(1);
+                self.player.position = self.random_free_floor();
+            }
+        }
+    }
+    // Reveals the hidden traps and secret doors around the player; searching finds all of them
+    // in PLAYER_SEARCH_RADIUS, otherwise the player might notice the ones right next to them
+    fn find_hidden(&mut self, searching: bool) {
+        let mut found_any = false;
+        for point in self.player.position.iter_within(PLAYER_SEARCH_RADIUS) {
+            if !self.level.tiles.in_bounds(point) {
+                continue;
+            }
+            let is_hidden = match self.level.tiles[point] {
+                Tile::Trap(trap) => !trap.found,
+                Tile::Door(DoorState::Secret) => true,
+                _ => false,
+            };
+            let noticed = is_hidden
+                && (searching
+                    || (point.distance_to(self.player.position) <= 1
+                        && self.rng.gen_range(0, 100) < PERCEPTION_CHANCE));
+            if !noticed {
+                continue;
+            }
+            match self.level.tiles[point] {
+                Tile::Trap(trap) => {
+                    self.level.tiles[point] = Tile::Trap(Trap {
+                        found: true,
+                        ..trap
+                    });
+                    self.events.push(Event::FoundTrap(trap.kind));
+                }
+                _ => {
+                    self.level.tiles[point] = Tile::Door(DoorState::Closed);
+                    self.events.push(Event::FoundSecretDoor);
+                }
+            }
+            found_any = true;
+        }
 
+        if searching && !found_any {
+            self.events.push(Event::FoundNothing);
+        }
+    }
+    // A floor tile nobody stands on
+    fn is_free_floor(&self, point: Point) -> bool {
+        self.level.tiles[point].is_walkable()
+            && !self.level.tiles[point].is_blocking()
+            && point != self.player.position
+            && self
+                .level
+                .enemies
+                .iter()
+                .all(|enemy| enemy.position != point)
+    }
+    // The thrown item flies towards the target until it hits an enemy or an obstacle,
+    // landing on the last free floor tile it passed over.
+    fn throw_item(&mut self, index: usize, target: Point) {
+        let path = self
+            .level
+            .projectile_path(self.player.position, target, THROW_RANGE);
+        // Nothing gets thrown into a wall. Otherwise the item falls back along its path
+        // to the closest spot it can lie on, the player's own one at worst,
+        // so that it doesn't take the place of a door.
+        // Hidden traps look like any other spot, so instead of being passed over,
+        // those it falls on get set off.
+        let mut set_off = vec![];
+        let landing_point = if path.is_empty() {
+            None
+        } else {
+            path.iter()
+                .rev()
+                .chain([&self.player.position])
+                .copied()
+                .find(|&p| match self.level.tiles[p] {
+                    Tile::Room => true,
+                    Tile::Trap(trap) if !trap.found => {
+                        set_off.push((p, trap));
+                        false
+                    }
+                    _ => false,
+                })
+        };
+        let hit_enemy = path.last().and_then(|&p| {
+            self.level
+                .enemies
+                .iter()
+                .position(|enemy| enemy.position == p)
+        });
+
+        if let Some(landing_point) = landing_point {
+            for (point, trap) in set_off {
+                self.level.tiles[point] = Tile::Trap(Trap {
+                    found: true,
+                    ..trap
+                });
+                self.events.push(Event::ThrowSetOffTrap(trap.kind));
+            }
+            let item = self.player.inventory.remove(index);
+            self.level.tiles[landing_point] = Tile::Item(item);
+            self.events.push(Event::ThrewItem(item));
+
+            if let Some(enemy_index) = hit_enemy {
+                let enemy = &mut self.level.enemies[enemy_index];
+                let damage_dealt = match item {
+                    PickUpItem::Gear(gear) if gear.slot() == GearSlot::Weapon => {
+                        gear.roll(&mut self.rng)
+                    }
+                    _ => self.rng.gen_range(1, 4),
+                };
+                enemy.health_points -= damage_dealt;
+                self.events
+                    .push(Event::HitWithThrow(enemy.kind, damage_dealt));
+            }
+        } else {
+            self.events.push(Event::CantThrowThere);
+        }
+    }
+    // Whether the game has as many levels, of the same size, as the given dungeon configuration
+    pub fn fits_dungeon(&self, config: &DungeonConfig) -> bool {
+        let dimensions = config.dimensions;
+        self.levels.len() == config.level_count as usize
+            && self
+                .levels
+                .iter()
+                .flatten()
+                .chain([&self.level])
+                .all(|level| {
+                    level.tiles.width() == dimensions.width
+                        && level.tiles.height() == dimensions.height
+                })
+    }
+    // Whether the down stairs of the current level lead out of the dungeon, with the Amulet of Yendor
+    pub fn is_last_level(&self) -> bool {
+        self.current_level >= dungeon_config().level_count
+    }
+    // The enemies in the player's field of view, the closest ones first
+    pub fn visible_enemies(&self) -> Vec<&Being> {
+        let mut enemies: Vec<&Being> = self
+            .level
+            .enemies
+            .iter()
+            .filter(|enemy| self.level.last_seen[enemy.position] == self.current_turn)
+            .collect();
+        enemies.sort_by_key(|enemy| enemy.position.distance_to(self.player.position));
+
+        enemies
+    }
+    // Takes the dead enemies off the level, the player getting their XP
+    fn collect_kills(&mut self) {
+        self.level.enemies.retain(|enemy| {
+            if enemy.health_points > 0 {
+                true
+            } else {
+                self.player.experience_points += enemy.experience_points;
+                self.events
+                    .push(Event::Killed(enemy.kind, enemy.experience_points));
+                false
+            }
+        });
+        while self.player.experience_points
+            >= experience_for_level(self.player.experience_level + 1)
+        {
+            self.player.level_up();
+            self.events
+                .push(Event::LeveledUp(self.player.experience_level));
+        }
+    }
+    // Lets everyone else act until the player gets enough energy to act again.
+    // Every tick each being gains as much energy as its speed,
+    // and everyone with enough energy acts, the most energetic first.
+    // A tick is a turn of world time: statuses wear off and the player gets hungrier by it,
+    // whoever's hasted or slowed.
+    fn run_until_player_turn(&mut self) {
+        // The player may have moved, or changed the level, since the last time
+        self.flee_map.take();
+        loop {
+            let mut actors: BinaryHeap<(i32, Reverse<Actor>)> = self
+                .level
+                .enemies
+                .iter()
+                .enumerate()
+                .filter(|(_, enemy)| enemy.energy >= ACTION_COST)
+                .map(|(i, enemy)| (enemy.energy, Reverse(Actor::Enemy(i))))
+                .collect();
+            if self.player.energy >= ACTION_COST {
+                actors.push((self.player.energy, Reverse(Actor::Player)));
+            }
+
+            while let Some((_, Reverse(actor))) = actors.pop() {
+                match actor {
+                    Actor::Player => return,
+                    Actor::Enemy(i) => {
+                        self.enemy_act(i);
+                        self.level.enemies[i].energy -= ACTION_COST;
+                        if self.level.enemies[i].energy >= ACTION_COST {
+                            actors.push((self.level.enemies[i].energy, Reverse(actor)));
+                        }
+
+                        if self.player.health_points <= 0 {
+                            return;
+                        }
+                    }
+                }
+            }
+
+            self.tick_statuses();
+            self.tick_hunger();
+            self.tick_regeneration();
+            self.collect_kills();
+            if self.player.health_points <= 0 {
+                return;
+            }
+
+            self.player.energy += self.player.current_speed();
+            for enemy in &mut self.level.enemies {
+                enemy.energy += enemy.current_speed();
+            }
+        }
+    }
+    // Poison and regeneration take their toll, and every status gets a turn closer to wearing off
+    fn tick_statuses(&mut self) {
+        for being in std::iter::once(&mut self.player).chain(self.level.enemies.iter_mut()) {
+            if being.statuses.has(StatusKind::Poison) {
+                being.health_points -= 1;
+            }
+            if being.statuses.has(StatusKind::Regeneration) {
+                being.heal(1);
+            }
+            let worn_off = being.statuses.tick();
+            if being.kind == BeingKind::Player {
+                self.events
+                    .extend(worn_off.into_iter().map(Event::StatusWoreOff));
+            }
+        }
+    }
+    // The player gets a turn hungrier, starving ones losing HP
+    fn tick_hunger(&mut self) {
+        let hunger = Hunger::of(self.player.nutrition);
+        self.player.nutrition = max(0, self.player.nutrition - 1);
+        let new_hunger = Hunger::of(self.player.nutrition);
+
+        if new_hunger != hunger {
+            self.events.push(Event::HungerChanged(new_hunger));
+        }
+        if new_hunger == Hunger::Starving {
+            self.player.health_points -= 1;
+        }
+    }
+    // Every tenth turn the beings heal a bit, unless the player is too hungry to
+    // (is_multiple_of would need a newer Rust than the rest of the game does)
+    #[allow(clippy::manual_is_multiple_of)]
+    fn tick_regeneration(&mut self) {
+        self.world_turn += 1;
+        if self.world_turn % 10 != 0 {
+            return;
+        }
+
+        if Hunger::of(self.player.nutrition) < Hunger::Weak {
+            self.player.bump_health();
+        }
+        self.level
+            .enemies
+            .iter_mut()
+            .for_each(|enemy| enemy.bump_health());
+    }
+    // The enemy's hit might put its status effect on the player
+    fn inflict(&mut self, i: usize, damage_dealt: i32) {
+        let infliction = self.level.enemies[i]
+            .kind
+            .monster()
+            .and_then(|monster| monster.inflicts);
+        if let Some(infliction) = infliction {
+            if damage_dealt > 0 && self.rng.gen_range(0, 100) < infliction.chance {
+                self.player
+                    .statuses
+                    .add(infliction.status, infliction.turns);
+                self.events.push(Event::GotStatus(infliction.status));
+            }
+        }
+    }
+    // Lets the enemy's behavior decide what to do, then carries it out
+    fn enemy_act(&mut self, i: usize) {
+        if self.level.enemies[i].statuses.has(StatusKind::Stun) {
+            return;
+        }
+        let enemy = &mut self.level.enemies[i];
+        if let Some(monster) = enemy.kind.monster() {
+            if !enemy.fleeing && monster.breaks_morale(enemy) {
+                enemy.fleeing = true;
+                self.events.push(Event::Fled(enemy.kind));
+            } else if enemy.fleeing && monster.regains_morale(enemy) {
+                enemy.fleeing = false;
+            }
+        }
+
+        let behavior = if self.level.enemies[i].fear_turns > 0 {
+            self.level.enemies[i].fear_turns -= 1;
+            &Fleeing
+        } else if self.level.enemies[i].fleeing {
+            &Fleeing
+        } else {
+            match self.level.enemies[i].kind.behavior() {
+                Some(behavior) => behavior,
+                None => return,
+            }
+        };
+        // Packs hunt together, whatever their members would do on their own
+        if (behavior.hunts() || self.level.enemies[i].pack.is_some())
+            && self.level.last_seen[self.level.enemies[i].position] == self.current_turn
+        {
+            // The rest of its pack learns where the player is too
+            let enemy = self.level.enemies[i].clone();
+            for (j, other) in self.level.enemies.iter_mut().enumerate() {
+                if j == i || other.is_pack_mate_of(&enemy) {
+                    other.memory = Some(Memory::new(self.player.position));
+                }
+            }
+        }
+
+        let intent = {
+            let world = World {
+                level: &self.level,
+                player: &self.player,
+                current_turn: self.current_turn,
+                flee_map: &self.flee_map,
+            };
+            match behavior.decide(&self.level.enemies[i], &world, &mut self.rng) {
+                // Behaviors can't make enemies go or reach where they couldn't
+                Intent::MoveTo(position)
+                    if !world.can_move(
+                        &self.level.enemies[i],
+                        self.level.enemies[i].position,
+                        position,
+                    ) =>
+                {
+                    Intent::Wait
+                }
+                Intent::AttackPlayer if !world.can_attack_player(&self.level.enemies[i]) => {
+                    Intent::Wait
+                }
+                Intent::ShootPlayer
+                    if !self.level.enemies[i].kind.monster().is_some_and(|monster| {
+                        world.can_shoot_player(&self.level.enemies[i], monster.range)
+                    }) =>
+                {
+                    Intent::Wait
+                }
+                intent => intent,
+            }
+        };
+        // A confused enemy stumbles around half of the time
+        let intent = match intent {
+            Intent::MoveTo(_)
+                if self.level.enemies[i].statuses.has(StatusKind::Confusion)
+                    && self.rng.gen_bool(0.5) =>
+            {
+                let position = self.level.enemies[i].position;
+                let direction = DIRECTIONS[self.rng.gen_range(0, DIRECTIONS.len())];
+                let world = World {
+                    level: &self.level,
+                    player: &self.player,
+                    current_turn: self.current_turn,
+                    flee_map: &self.flee_map,
+                };
+                if world.can_move(&self.level.enemies[i], position, position + direction) {
+                    Intent::MoveTo(position + direction)
+                } else {
+                    Intent::Wait
+                }
+            }
+            intent => intent,
+        };
+
+        match intent {
+            Intent::Wait => {}
+            // Opening a door takes the whole turn
+            Intent::MoveTo(position)
+                if self.level.tiles[position] == Tile::Door(DoorState::Closed) =>
+            {
+                self.level.tiles[position] = Tile::Door(DoorState::Open);
+            }
+            Intent::MoveTo(position) => {
+                self.level.enemies[i].position = position;
+            }
+            Intent::AttackPlayer => {
+                let damage_dealt = self.level.enemies[i].fight(&mut self.player, &mut self.rng);
+                self.events
+                    .push(Event::GotAttacked(self.level.enemies[i].kind, damage_dealt));
+                self.inflict(i, damage_dealt);
+            }
+            Intent::ShootPlayer => {
+                let damage_dealt = self.level.enemies[i].fight(&mut self.player, &mut self.rng);
+                self.events
+                    .push(Event::GotShot(self.level.enemies[i].kind, damage_dealt));
+                self.inflict(i, damage_dealt);
+            }
+        }
+
+        // Once a hunter gets to where it last saw the player, or gets stuck on the way,
+        // it searches around for a while, then gives up
+        let enemy = &mut self.level.enemies[i];
+        if let Some(memory) = &mut enemy.memory {
+            if enemy.position == memory.player_position || intent == Intent::Wait {
+                memory.searching = true;
+            }
+            if memory.searching {
+                memory.search_turns -= 1;
+                if memory.search_turns == 0 {
+                    enemy.memory = None;
+                    self.events.push(Event::LostTrail(enemy.kind));
+                }
+            }
+        }
+    }
+    pub fn next_turn(&mut self, action: PlayerAction) {
+        self.actions.push(action);
         self.events = vec![];
+
+        // Keys get used by walking into locked doors, so trying to use one takes no time
+        if let PlayerAction::UseItem(index) = action {
+            if self.player.inventory.get(index) == Some(&PickUpItem::Key) {
+                self.events.push(Event::KeyUse);
+                return;
+            }
+        }
+
         self.current_turn += 1;
 
+        // A stunned player loses their turn
+        let action = if self.player.statuses.has(StatusKind::Stun) {
+            self.events.push(Event::Stunned);
+            PlayerAction::MoveBy(Point::new(0, 0))
+        } else {
+            action
+        };
+
         match action {
             PlayerAction::MoveBy(move_by) => {
+                // and a confused one stumbles in a random direction half of the time
+                let move_by = if move_by != Point::new(0, 0)
+                    && self.player.statuses.has(StatusKind::Confusion)
+                    && self.rng.gen_bool(0.5)
+                {
+                    DIRECTIONS[self.rng.gen_range(0, DIRECTIONS.len())]
+                } else {
+                    move_by
+                };
                 let new_position = self.player.position + move_by;
-                if new_position.in_bounds() && self.level.tiles[new_position].is_walkable() {
+                if self.level.tiles.in_bounds(new_position)
+                    && matches!(
+                        self.level.tiles[new_position],
+                        Tile::Door(DoorState::Closed | DoorState::Locked)
+                    )
+                    && !self.player.position.is_diagonal_to(new_position)
+                {
+                    self.open_door(new_position);
+                } else if self.level.can_step(self.player.position, new_position) {
                     if let Some(enemy) = self
                         .level
                         .enemies
                         .iter_mut()
                         .find(|enemy| enemy.position == new_position)
                     {
-                        let damage_dealt = self.player.fight(enemy);
+                        let damage_dealt = self.player.fight(enemy, &mut self.rng);
                         self.events.push(Event::Attacked(enemy.kind, damage_dealt));
                     } else {
                         self.player.position = new_position;
+                        if let Tile::Trap(trap) = self.level.tiles[new_position] {
+                            self.trigger_trap(trap);
+                        }
                     }
                 }
             }
             PlayerAction::Select => {
                 if let Tile::Stairs(progression) = self.level.tiles[self.player.position] {
                     if self.current_level + progression > 0 {
-                        self.current_level += progression;
-
-                        if self.current_level > LEVEL_COUNT {
+                        if progression > 0 && self.is_last_level() {
+                            self.current_level += progression;
                             self.events.push(Event::Won(self.player.experience_points));
                             self.current_level += progression;
                             return;
                         } else {
-                            self.levels[(self.current_level - progression - 1) as usize] =
-                                Some(self.level.clone());
-
-                            self.level = self.levels[(self.current_level - 1) as usize]
-                                .clone()
-                                .unwrap_or_else(|| Level::generate(self.current_level));
+                            self.change_level(progression);
                             self.player.position = if progression > 0 {
                                 self.level.up_stairs
                             } else {
@@ -96,109 +828,73 @@ impl GameContext {
 
                         return;
                     }
-                } else if let Tile::Item(item) = self.level.tiles[self.player.position] {
-                    match item {
-                        PickUpItem::HealthBoost => {
-                            self.player.max_health_points += 1;
-                            self.player.health_points = self.player.max_health_points;
+                } else {
+                    self.pick_up();
+                }
+            }
+            PlayerAction::PickUp => {
+                self.pick_up();
+            }
+            PlayerAction::UseItem(index) => {
+                if index < self.player.inventory.len() {
+                    let item = self.player.inventory.remove(index);
+                    self.use_item(item);
+                }
+            }
+            PlayerAction::DropItem(index) => {
+                if index < self.player.inventory.len() {
+                    match self.level.tiles[self.player.position] {
+                        Tile::Room => {
+                            let item = self.player.inventory.remove(index);
+                            self.level.tiles[self.player.position] = Tile::Item(item);
+                            self.events.push(Event::DroppedItem(item));
                         }
-                        PickUpItem::AttackBoost => {
-                            self.player.max_attack += 1;
+                        // A hidden trap looks like the floor, so bending down over it sets it off
+                        Tile::Trap(trap) if !trap.found => {
+                            self.trigger_trap(trap);
                         }
-                        PickUpItem::ShieldBoost => {
-                            self.player.max_shield += 1;
+                        _ => {
+                            self.events.push(Event::CantDropHere);
                         }
                     }
-                    self.level.tiles[self.player.position] = Tile::Room;
                 }
             }
-        }
-        self.level.enemies.retain(|enemy| {
-            if enemy.health_points > 0 {
-                true
-            } else {
-                self.player.experience_points += enemy.experience_points;
-                self.events
-                    .push(Event::Killed(enemy.kind, enemy.experience_points));
-                false
+            PlayerAction::ThrowItem(index, target) => {
+                if index < self.player.inventory.len() {
+                    self.throw_item(index, target);
+                }
             }
-        });
-        self.update_fov();
-
-        // This generates the action of every enemy in the turn. Due to rust borrow rules,
-        // you can't have at the same time both
-        // a mutable reference to an element of the array of enemies
-        // and an immutable one to the whole array.
-        // To get around this, I inlined the whole process here,
-        // with a for loop based on the index of the enemies in the level's array.
-        for i in 0..self.level.enemies.len() {
-            match self.level.enemies[i].kind {
-                // Regular enemies that chase the player when in sight
-                BeingKind::Gnoll | BeingKind::Kestrel | BeingKind::Troll => {
-                    if self.level.last_seen[self.level.enemies[i].position] == self.current_turn {
-                        if let Some((to_player, _)) = astar(
-                            &self.level.enemies[i].position,
-                            |&point| {
-                                point
-                                    .iter_neighbors()
-                                    .filter(|&p| self.level.tiles[p].is_walkable())
-                                    .filter(|&p| self.level.enemies.iter().all(|e| e.position != p))
-                                    .map(|p| (p, 1))
-                            },
-                            |&Point { y, x }| {
-                                x.abs_diff(self.player.position.x)
-                                    + y.abs_diff(self.player.position.y)
-                            },
-                            |&point| point == self.player.position,
-                        ) {
-                            if to_player[1] == self.player.position {
-                                let damage_dealt = self.level.enemies[i].fight(&mut self.player);
-                                self.events.push(Event::GotAttacked(
-                                    self.level.enemies[i].kind,
-                                    damage_dealt,
-                                ));
-                            } else {
-                                self.level.enemies[i].position = to_player[1];
-                            }
-                        }
-                    }
+            PlayerAction::Search => {
+                self.find_hidden(true);
+            }
+            PlayerAction::OpenDoor(direction) => {
+                self.open_door(self.player.position + direction);
+            }
+            PlayerAction::CloseDoor(direction) => {
+                self.close_door(self.player.position + direction);
+            }
+            PlayerAction::Unequip(slot) => match self.player.equipment.get(slot) {
+                None => {
+                    self.events.push(Event::NothingEquipped(slot));
                 }
-                // Chaotically moving enemies
-                BeingKind::Bat | BeingKind::Emu | BeingKind::Zombie => {
-                    if self.level.enemies[i]
-                        .position
-                        .is_neighboring(self.player.position)
-                    {
-                        let damage_dealt = self.level.enemies[i].fight(&mut self.player);
-                        self.events
-                            .push(Event::GotAttacked(self.level.enemies[i].kind, damage_dealt));
-                    } else {
-                        let possible_positions: Vec<Point> = self.level.enemies[i]
-                            .position
-                            .iter_neighbors()
-                            .filter(|&p| self.level.tiles[p].is_walkable())
-                            .filter(|&p| self.level.enemies.iter().all(|enemy| enemy.position != p))
-                            .collect();
-                        if possible_positions.len() > 0 {
-                            self.level.enemies[i].position =
-                                possible_positions[rng.gen_range(0, possible_positions.len())];
-                        }
-                    }
+                Some(_) if self.player.inventory.len() >= INVENTORY_SIZE => {
+                    self.events.push(Event::InventoryFull);
                 }
-                // Static enemies
-                BeingKind::AnimatedStatue | BeingKind::LazyImp | BeingKind::StoneSatan => {
-                    if self.level.enemies[i]
-                        .position
-                        .is_neighboring(self.player.position)
-                    {
-                        let damage_dealt = self.level.enemies[i].fight(&mut self.player);
-                        self.events
-                            .push(Event::GotAttacked(self.level.enemies[i].kind, damage_dealt));
-                    }
+                Some(gear) => {
+                    self.player.equipment.unequip(slot);
+                    self.player.inventory.push(PickUpItem::Gear(gear));
+                    self.events.push(Event::Unequipped(gear));
                 }
-                _ => {}
-            }
+            },
         }
+        if !matches!(action, PlayerAction::Search) {
+            self.find_hidden(false);
+        }
+        self.collect_kills();
+        self.update_fov();
+
+        self.player.energy -= ACTION_COST;
+        self.run_until_player_turn();
 
         if self.player.health_points <= 0 {
             self.events.push(Event::Died(self.player.experience_points));
@@ -212,20 +908,874 @@ impl GameContext {
             Tile::Stairs(1) => {
                 self.events.push(Event::OnItem(None));
             }
-            Tile::Stairs(-1) => {
-                if self.current_level > 1 {
-                    self.events.push(Event::OnUpstairs);
-                }
+            Tile::Stairs(-1) if self.current_level > 1 => {
+                self.events.push(Event::OnUpstairs);
             }
             _ => {}
         }
+    }
+}
+impl Default for GameContext {
+    fn default() -> Self {
+        GameContext::new()
+    }
+}
 
-        if self.current_turn % 10 == 0 {
-            self.player.bump_health();
-            self.level
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        being::{PackMember, PackRole},
+        beingkind::BeingKind,
+        gear::{Gear, GearKind},
+        room::Room,
+        trap::TrapKind,
+        HUNGRY_BELOW, SEARCH_DURATION, WEAK_BELOW,
+    };
+
+    fn play(context: &mut GameContext) {
+        let actions = [
+            PlayerAction::MoveBy(Point::new(0, 1)),
+            PlayerAction::MoveBy(Point::new(1, 0)),
+            PlayerAction::MoveBy(Point::new(0, 0)),
+            PlayerAction::Select,
+            PlayerAction::MoveBy(Point::new(-1, 0)),
+            PlayerAction::MoveBy(Point::new(0, -1)),
+        ];
+        for _ in 0..20 {
+            for action in actions {
+                context.next_turn(action);
+            }
+        }
+    }
+
+    // A context whose level is one big empty room, with the player in the middle
+    pub fn arena() -> GameContext {
+        let mut context = GameContext::new_with_seed(0);
+        let (width, height) = (context.level.tiles.width(), context.level.tiles.height());
+        context.level.tiles = TileArray::new(width, height, Tile::Wall);
+        context
+            .level
+            .tiles
+            .carve_room(Room::new(0, 0, width - 1, height - 1));
+        context.level.enemies.clear();
+        context.player.position = Point::new(width / 2, height / 2);
+        context.update_fov();
+
+        context
+    }
+
+    #[test]
+    fn gamecontext_same_seed_same_game() {
+        let mut context1 = GameContext::new_with_seed(42);
+        let mut context2 = GameContext::new_with_seed(42);
+        assert_eq!(format!("{:?}", context1), format!("{:?}", context2));
+
+        play(&mut context1);
+        play(&mut context2);
+        assert_eq!(format!("{:?}", context1), format!("{:?}", context2));
+    }
+    #[test]
+    fn gamecontext_inventory() {
+        let mut context = GameContext::new_with_seed(5);
+        context.level.enemies.clear();
+        let position = context.level.tiles.generate_floor_point(&mut context.rng);
+        context.player.position = position;
+
+        context.level.tiles[position] = Tile::Item(PickUpItem::AttackBoost);
+        context.next_turn(PlayerAction::PickUp);
+        assert_eq!(context.player.inventory, vec![PickUpItem::AttackBoost]);
+        assert_eq!(context.level.tiles[position], Tile::Room);
+
+        context.next_turn(PlayerAction::DropItem(0));
+        assert!(context.player.inventory.is_empty());
+        assert_eq!(
+            context.level.tiles[position],
+            Tile::Item(PickUpItem::AttackBoost)
+        );
+
+        context.next_turn(PlayerAction::Select);
+        let max_attack = context.player.max_attack;
+        context.next_turn(PlayerAction::UseItem(0));
+        assert!(context.player.inventory.is_empty());
+        assert_eq!(context.player.max_attack, max_attack + 1);
+
+        context.player.inventory = vec![PickUpItem::HealthBoost; INVENTORY_SIZE];
+        context.level.tiles[position] = Tile::Item(PickUpItem::ShieldBoost);
+        context.next_turn(PlayerAction::PickUp);
+        assert_eq!(context.player.inventory.len(), INVENTORY_SIZE);
+        assert!(matches!(context.events[0], Event::InventoryFull));
+    }
+    #[test]
+    fn gamecontext_consumables() {
+        let mut context = GameContext::new_with_seed(9);
+        context.level.enemies.clear();
+
+        context.player.health_points = 1;
+        context.player.inventory = vec![
+            PickUpItem::HealingPotion,
+            PickUpItem::MagicMappingScroll,
+            PickUpItem::TeleportScroll,
+        ];
+
+        context.next_turn(PlayerAction::UseItem(0));
+        assert_eq!(context.player.health_points, 1 + HEALING_POTION_HP);
+        assert!(matches!(
+            context.events[0],
+            Event::Healed(HEALING_POTION_HP)
+        ));
+
+        context.next_turn(PlayerAction::UseItem(0));
+        assert!(context.level.last_seen[context.level.down_stairs] > 0);
+
+        let position = context.player.position;
+        context.next_turn(PlayerAction::UseItem(0));
+        assert_ne!(context.player.position, position);
+        assert!(context.player.inventory.is_empty());
+    }
+    #[test]
+    fn gamecontext_equipment() {
+        let mut context = GameContext::new_with_seed(9);
+        context.level.enemies.clear();
+        let dagger = Gear::new(GearKind::Dagger, 0);
+        let sword = Gear::new(GearKind::Sword, 2);
+        context.player.inventory = vec![PickUpItem::Gear(dagger), PickUpItem::Gear(sword)];
+
+        context.next_turn(PlayerAction::UseItem(0));
+        context.next_turn(PlayerAction::UseItem(0));
+        assert_eq!(context.player.equipment.weapon, Some(sword));
+        assert_eq!(context.player.inventory, vec![PickUpItem::Gear(dagger)]);
+
+        context.next_turn(PlayerAction::Unequip(GearSlot::Weapon));
+        assert_eq!(context.player.equipment.weapon, None);
+        assert_eq!(context.player.inventory.len(), 2);
+        assert_eq!(context.player.max_attack, Being::new_player().max_attack);
+    }
+    #[test]
+    fn gamecontext_unequip_empty_slot() {
+        let mut context = GameContext::new_with_seed(9);
+        context.level.enemies.clear();
+        context.player.inventory = vec![PickUpItem::FoodRation; INVENTORY_SIZE];
+
+        context.next_turn(PlayerAction::Unequip(GearSlot::Armour));
+        assert!(matches!(
+            context.events[0],
+            Event::NothingEquipped(GearSlot::Armour)
+        ));
+
+        context.player.equipment.armour = Some(Gear::new(GearKind::ChainMail, 0));
+        context.next_turn(PlayerAction::Unequip(GearSlot::Armour));
+        assert!(matches!(context.events[0], Event::InventoryFull));
+        assert!(context.player.equipment.armour.is_some());
+    }
+    #[test]
+    fn gamecontext_fear_scroll() {
+        let mut context = GameContext::new_with_seed(9);
+        let mut enemy = context.level.enemies[0].clone();
+        enemy.position = context.player.position + Point::new(1, 0);
+        context.level.enemies = vec![enemy];
+        context.level.tiles[context.player.position + Point::new(1, 0)] = Tile::Room;
+
+        context.player.inventory = vec![PickUpItem::FearScroll];
+        context.next_turn(PlayerAction::UseItem(0));
+
+        assert!(matches!(context.events[0], Event::Frightened(1)));
+        assert_eq!(context.level.enemies[0].fear_turns, FEAR_DURATION - 1);
+        assert_eq!(
+            context.player.health_points,
+            context.player.max_health_points
+        );
+    }
+    #[test]
+    fn gamecontext_throw_item() {
+        let mut context = arena();
+        let mut enemy = Being::new_of_kind(
+            BeingKind::named("Animated Statue").unwrap(),
+            context.player.position + Point::new(3, 2),
+            &mut context.rng,
+        );
+        enemy.max_shield = 100;
+        context.level.enemies = vec![enemy];
+        context.update_fov();
+        context.player.inventory = vec![PickUpItem::HealthBoost, PickUpItem::ShieldBoost];
+
+        let target = context.visible_enemies()[0].position;
+        context.next_turn(PlayerAction::ThrowItem(1, target));
+        assert!(matches!(context.events[1], Event::HitWithThrow(_, _)));
+        assert_eq!(
+            context.level.tiles[target],
+            Tile::Item(PickUpItem::ShieldBoost)
+        );
+        assert_eq!(context.player.inventory, vec![PickUpItem::HealthBoost]);
+
+        context.level.tiles[context.player.position + Point::new(0, -1)] = Tile::Wall;
+        context.next_turn(PlayerAction::ThrowItem(
+            0,
+            context.player.position + Point::new(0, -5),
+        ));
+        assert!(matches!(context.events[0], Event::CantThrowThere));
+        assert_eq!(context.player.inventory, vec![PickUpItem::HealthBoost]);
+    }
+    #[test]
+    fn gamecontext_throw_item_onto_hidden_trap() {
+        let mut context = arena();
+        let trap = context.player.position + Point::new(3, 0);
+        context.level.tiles[trap] = Tile::Trap(Trap::hidden(TrapKind::Alarm));
+        context.player.inventory = vec![PickUpItem::HealthBoost];
+
+        // The trap doesn't get passed over like a wall, it goes off
+        context.next_turn(PlayerAction::ThrowItem(0, trap));
+        assert!(matches!(
+            context.events[..2],
+            [
+                Event::ThrowSetOffTrap(TrapKind::Alarm),
+                Event::ThrewItem(PickUpItem::HealthBoost)
+            ]
+        ));
+        assert_eq!(
+            context.level.tiles[trap],
+            Tile::Trap(Trap {
+                kind: TrapKind::Alarm,
+                found: true
+            })
+        );
+        assert_eq!(
+            context.level.tiles[trap + Point::new(-1, 0)],
+            Tile::Item(PickUpItem::HealthBoost)
+        );
+    }
+    #[test]
+    fn gamecontext_throw_item_into_doorway() {
+        let mut context = arena();
+        let doorway = context.player.position + Point::new(1, 0);
+        context.level.tiles[doorway] = Tile::Door(DoorState::Open);
+        let mut enemy = Being::new_of_kind(
+            BeingKind::named("Animated Statue").unwrap(),
+            doorway,
+            &mut context.rng,
+        );
+        enemy.max_shield = 100;
+        context.level.enemies = vec![enemy];
+        context.update_fov();
+        context.player.inventory = vec![PickUpItem::ShieldBoost];
+
+        context.next_turn(PlayerAction::ThrowItem(0, doorway));
+        assert!(matches!(context.events[1], Event::HitWithThrow(_, _)));
+        assert_eq!(context.level.tiles[doorway], Tile::Door(DoorState::Open));
+        assert_eq!(
+            context.level.tiles[context.player.position],
+            Tile::Item(PickUpItem::ShieldBoost)
+        );
+        assert!(context.player.inventory.is_empty());
+    }
+    #[test]
+    fn gamecontext_visible_enemies_closest_first() {
+        let mut context = arena();
+        let player_position = context.player.position;
+        for offset in [Point::new(5, 0), Point::new(0, -4), Point::new(3, 3)] {
+            let mut enemy = Being::new_player();
+            enemy.kind = BeingKind::named("Animated Statue").unwrap();
+            enemy.position = player_position + offset;
+            context.level.enemies.push(enemy);
+        }
+        context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+
+        let positions: Vec<Point> = context
+            .visible_enemies()
+            .iter()
+            .map(|enemy| enemy.position)
+            .collect();
+        assert_eq!(
+            positions,
+            [Point::new(3, 3), Point::new(0, -4), Point::new(5, 0)]
+                .map(|offset| player_position + offset)
+        );
+    }
+    #[test]
+    fn gamecontext_different_seed_different_game() {
+        let context1 = GameContext::new_with_seed(1);
+        let context2 = GameContext::new_with_seed(2);
+
+        assert_ne!(
+            format!("{:?}", context1.level),
+            format!("{:?}", context2.level)
+        );
+    }
+    #[test]
+    fn gamecontext_diagonal_moves() {
+        let mut context = arena();
+        let start = context.player.position;
+
+        context.next_turn(PlayerAction::MoveBy(Point::new(1, 1)));
+        assert_eq!(context.player.position, start + Point::new(1, 1));
+
+        // Doors can't be entered or left diagonally
+        let door = context.player.position + Point::new(1, -1);
+        context.level.tiles[door] = Tile::Door(DoorState::Open);
+        context.next_turn(PlayerAction::MoveBy(Point::new(1, -1)));
+        assert_eq!(context.player.position, start + Point::new(1, 1));
+        context.next_turn(PlayerAction::MoveBy(Point::new(0, -1)));
+        context.next_turn(PlayerAction::MoveBy(Point::new(1, 0)));
+        assert_eq!(context.player.position, door);
+        context.next_turn(PlayerAction::MoveBy(Point::new(1, 1)));
+        assert_eq!(context.player.position, door);
+
+        // Enemies attack diagonally, but not from a door
+        let mut context = arena();
+        let enemy_position = context.player.position + Point::new(-1, -1);
+        context.level.enemies = vec![Being::new_of_kind(
+            BeingKind::named("Stone Satan").unwrap(),
+            enemy_position,
+            &mut context.rng,
+        )];
+        context.level.enemies[0].max_attack = 100;
+        context.player.health_points = 1000;
+        context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        assert!(matches!(context.events[0], Event::GotAttacked(_, _)));
+
+        context.level.tiles[enemy_position] = Tile::Door(DoorState::Open);
+        context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        assert!(context.events.is_empty());
+    }
+    #[test]
+    fn gamecontext_doors() {
+        let mut context = arena();
+        let start = context.player.position;
+        let door = start + Point::new(1, 0);
+        context.level.tiles[door] = Tile::Door(DoorState::Locked);
+
+        context.next_turn(PlayerAction::MoveBy(Point::new(1, 0)));
+        assert!(matches!(context.events[0], Event::DoorLocked));
+        assert_eq!(context.player.position, start);
+
+        // Trying to use a key only tells how, leaving the inventory and the time alone
+        context.player.inventory = vec![PickUpItem::Key, PickUpItem::FoodRation];
+        let (turn, nutrition) = (context.current_turn, context.player.nutrition);
+        context.next_turn(PlayerAction::UseItem(0));
+        assert!(matches!(context.events[..], [Event::KeyUse]));
+        assert_eq!(
+            context.player.inventory,
+            vec![PickUpItem::Key, PickUpItem::FoodRation]
+        );
+        assert_eq!(context.current_turn, turn);
+        assert_eq!(context.player.nutrition, nutrition);
+        context.player.inventory.pop();
+        context.next_turn(PlayerAction::MoveBy(Point::new(1, 0)));
+        assert!(matches!(context.events[0], Event::UnlockedDoor));
+        assert!(context.player.inventory.is_empty());
+        assert_eq!(context.level.tiles[door], Tile::Door(DoorState::Open));
+
+        context.next_turn(PlayerAction::MoveBy(Point::new(1, 0)));
+        assert_eq!(context.player.position, door);
+        context.next_turn(PlayerAction::CloseDoor(Point::new(0, 0)));
+        assert!(matches!(context.events[0], Event::DoorBlocked));
+        context.next_turn(PlayerAction::MoveBy(Point::new(-1, 0)));
+        context.next_turn(PlayerAction::CloseDoor(Point::new(1, 0)));
+        assert!(matches!(context.events[0], Event::ClosedDoor));
+        assert_eq!(context.level.tiles[door], Tile::Door(DoorState::Closed));
+        assert_ne!(
+            context.level.last_seen[door + Point::new(1, 0)],
+            context.current_turn
+        );
+
+        context.next_turn(PlayerAction::OpenDoor(Point::new(1, 0)));
+        assert!(matches!(context.events[0], Event::OpenedDoor));
+        assert_eq!(
+            context.level.last_seen[door + Point::new(1, 0)],
+            context.current_turn
+        );
+        context.next_turn(PlayerAction::OpenDoor(Point::new(0, 1)));
+        assert!(matches!(context.events[0], Event::NoDoorThere));
+    }
+    #[test]
+    fn gamecontext_speed() {
+        let mut context = arena();
+        for (kind, offset) in [
+            (BeingKind::named("Kestrel").unwrap(), Point::new(1, 0)),
+            (BeingKind::named("Zombie").unwrap(), Point::new(-1, 0)),
+        ] {
+            let enemy =
+                Being::new_of_kind(kind, context.player.position + offset, &mut context.rng);
+            context.level.enemies.push(enemy);
+        }
+        context.player.health_points = 1000;
+
+        let count_attacks = |context: &mut GameContext, turns| {
+            let mut attacks = (0, 0);
+            for _ in 0..turns {
+                context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+                for event in &context.events {
+                    match event {
+                        Event::GotAttacked(kind, _) if kind.glyph() == 'K' => attacks.0 += 1,
+                        Event::GotAttacked(kind, _) if kind.glyph() == 'Z' => attacks.1 += 1,
+                        _ => {}
+                    }
+                }
+            }
+            attacks
+        };
+
+        assert_eq!(count_attacks(&mut context, 4), (8, 2));
+
+        // A hasted player gets two moves for every move of the others
+        context.player.speed *= 2;
+        assert_eq!(count_attacks(&mut context, 4), (4, 1));
+    }
+    #[test]
+    fn gamecontext_chaser_loses_the_trail() {
+        let mut context = arena();
+        let enemy = Being::new_of_kind(
+            BeingKind::named("Gnoll").unwrap(),
+            context.player.position + Point::new(4, 0),
+            &mut context.rng,
+        );
+        context.level.enemies.push(enemy);
+        context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        assert_eq!(
+            context.level.enemies[0].memory.unwrap().player_position,
+            context.player.position
+        );
+
+        // The player walls themselves in, out of the Gnoll's sight and reach
+        for neighbor in context.player.position.iter_neighbors() {
+            context.level.tiles[neighbor] = Tile::Wall;
+        }
+        let mut lost_trail = false;
+        for _ in 0..(SEARCH_DURATION + 5) {
+            context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+            lost_trail |= context
+                .events
+                .iter()
+                .any(|event| matches!(event, Event::LostTrail(_)));
+        }
+        assert!(lost_trail);
+        assert!(context.level.enemies[0].memory.is_none());
+    }
+    #[test]
+    fn gamecontext_packs_share_knowledge() {
+        let mut context = arena();
+        let gnoll = BeingKind::named("Gnoll").unwrap();
+        for (member, offset, role) in [
+            (0, Point::new(4, 0), PackRole::Leader),
+            (1, Point::new(-4, 0), PackRole::Follower),
+        ] {
+            let mut enemy =
+                Being::new_of_kind(gnoll, context.player.position + offset, &mut context.rng);
+            enemy.pack = Some(PackMember {
+                pack: 0,
+                member,
+                role,
+            });
+            context.level.enemies.push(enemy);
+        }
+        // The follower can't see the player
+        context.level.tiles[context.player.position + Point::new(-1, 0)] = Tile::Wall;
+        context.level.tiles[context.player.position + Point::new(-1, -1)] = Tile::Wall;
+        context.level.tiles[context.player.position + Point::new(-1, 1)] = Tile::Wall;
+
+        context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        for enemy in &context.level.enemies {
+            assert_eq!(
+                enemy.memory.unwrap().player_position,
+                context.player.position
+            );
+        }
+    }
+    #[test]
+    fn gamecontext_bat_swarms_hunt_together() {
+        let mut context = arena();
+        context.player.max_health_points = 1000;
+        context.player.health_points = 1000;
+        let player_position = context.player.position;
+        let bat = BeingKind::named("Bat").unwrap();
+        for (member, offset) in [
+            (0, Point::new(3, 0)),
+            (1, Point::new(-6, -1)),
+            (2, Point::new(-6, 1)),
+        ] {
+            let mut enemy = Being::new_of_kind(bat, player_position + offset, &mut context.rng);
+            enemy.pack = Some(PackMember {
+                pack: 0,
+                member,
+                role: if member == 0 {
+                    PackRole::Leader
+                } else {
+                    PackRole::Follower
+                },
+            });
+            context.level.enemies.push(enemy);
+        }
+        // Only the leader sees the player; the others are behind a wall they have to go around
+        for dy in -5..=5 {
+            context.level.tiles[player_position + Point::new(-3, dy)] = Tile::Wall;
+        }
+
+        context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        assert!(context
+            .level
+            .enemies
+            .iter()
+            .all(|enemy| enemy.memory.is_some()));
+        for _ in 0..30 {
+            if context
+                .level
                 .enemies
-                .iter_mut()
-                .for_each(|enemy| enemy.bump_health());
+                .iter()
+                .all(|enemy| enemy.position.is_neighboring(player_position))
+            {
+                break;
+            }
+            context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        }
+        assert!(context
+            .level
+            .enemies
+            .iter()
+            .all(|enemy| enemy.position.is_neighboring(player_position)));
+    }
+    #[test]
+    fn gamecontext_level_packs() {
+        let mut rng = GameRng::seed_from_u64(0);
+        let mut packs_seen = 0;
+        for level in 1..=10 {
+            let level = Level::generate(level, &mut rng);
+            for pack in 0..10 {
+                let members: Vec<&Being> = level
+                    .enemies
+                    .iter()
+                    .filter(|enemy| enemy.pack.map(|member| member.pack) == Some(pack))
+                    .collect();
+                if members.is_empty() {
+                    continue;
+                }
+                packs_seen += 1;
+                assert_eq!(
+                    members
+                        .iter()
+                        .filter(|enemy| enemy.pack.unwrap().role == PackRole::Leader)
+                        .count(),
+                    1
+                );
+                assert!(members.iter().all(|enemy| enemy.kind == members[0].kind));
+            }
+        }
+        assert!(packs_seen > 0);
+    }
+    #[test]
+    fn gamecontext_status_effects() {
+        let mut context = arena();
+        let start = context.player.position;
+        context.player.statuses.add(StatusKind::Poison, 2);
+        context.player.statuses.add(StatusKind::Stun, 1);
+
+        // Stunned, the player can't move
+        context.next_turn(PlayerAction::MoveBy(Point::new(1, 0)));
+        assert_eq!(context.player.position, start);
+        assert!(matches!(context.events[0], Event::Stunned));
+        assert_eq!(
+            context.player.health_points,
+            context.player.max_health_points - 1
+        );
+
+        context.next_turn(PlayerAction::MoveBy(Point::new(1, 0)));
+        assert_eq!(context.player.position, start + Point::new(1, 0));
+        assert!(context
+            .events
+            .iter()
+            .any(|event| matches!(event, Event::StatusWoreOff(StatusKind::Poison))));
+        assert_eq!(
+            context.player.health_points,
+            context.player.max_health_points - 2
+        );
+
+        // Confused, they stumble around
+        context.player.statuses.add(StatusKind::Confusion, 20);
+        let mut stumbled = false;
+        for _ in 0..10 {
+            let position = context.player.position;
+            context.next_turn(PlayerAction::MoveBy(Point::new(0, 1)));
+            context.next_turn(PlayerAction::MoveBy(Point::new(0, -1)));
+            stumbled |= context.player.position != position;
         }
+        assert!(stumbled);
+
+        // Blind, they only see what's next to them
+        context.player.statuses.add(StatusKind::Blindness, 5);
+        context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        let position = context.player.position;
+        assert_eq!(
+            context.level.last_seen[position + Point::new(1, 1)],
+            context.current_turn
+        );
+        assert!(context.level.last_seen[position + Point::new(2, 0)] < context.current_turn);
+    }
+    #[test]
+    fn gamecontext_statuses_last_in_world_time() {
+        let mut context = arena();
+        context.player.statuses.add(StatusKind::Haste, 10);
+        context.player.statuses.add(StatusKind::Poison, 4);
+
+        // Hasted, the player acts twice for every turn the poison takes its toll
+        for _ in 0..4 {
+            context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        }
+        assert_eq!(
+            context.player.health_points,
+            context.player.max_health_points - 2
+        );
+        for _ in 0..4 {
+            context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        }
+        assert_eq!(
+            context.player.health_points,
+            context.player.max_health_points - 4
+        );
+        assert!(!context.player.statuses.has(StatusKind::Poison));
+        assert!(context.player.statuses.has(StatusKind::Haste));
+
+        // Poison kills enemies just the same, the player getting the XP
+        let mut enemy = Being::new_of_kind(
+            BeingKind::named("Bat").unwrap(),
+            context.player.position + Point::new(5, 0),
+            &mut context.rng,
+        );
+        enemy.health_points = 1;
+        enemy.statuses.add(StatusKind::Poison, 5);
+        let experience_points = context.player.experience_points + enemy.experience_points;
+        context.level.enemies.push(enemy);
+        for _ in 0..2 {
+            context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        }
+        assert!(context.level.enemies.is_empty());
+        assert_eq!(context.player.experience_points, experience_points);
+    }
+    #[test]
+    fn gamecontext_blink_potion() {
+        let mut context = arena();
+        let start = context.player.position;
+        context.player.inventory = vec![PickUpItem::BlinkPotion, PickUpItem::BlinkPotion];
+        context.next_turn(PlayerAction::UseItem(0));
+        assert!(matches!(context.events[0], Event::Blinked));
+        assert_ne!(context.player.position, start);
+
+        // Boxed in, there's nowhere to blink to
+        let position = context.player.position;
+        for neighbor in position.iter_neighbors() {
+            context.level.tiles[neighbor] = Tile::Wall;
+        }
+        context.next_turn(PlayerAction::UseItem(0));
+        assert!(matches!(context.events[0], Event::NowhereToBlink));
+        assert_eq!(context.player.position, position);
+        assert!(context.player.inventory.is_empty());
+    }
+    #[test]
+    fn gamecontext_speed_potion() {
+        let mut context = arena();
+        context.player.inventory.push(PickUpItem::SpeedPotion);
+        context.next_turn(PlayerAction::UseItem(0));
+        assert!(context.player.statuses.has(StatusKind::Haste));
+        assert_eq!(context.player.current_speed(), context.player.speed * 2);
+    }
+    #[test]
+    fn gamecontext_level_up() {
+        let mut context = arena();
+        let mut enemy = Being::new_of_kind(
+            BeingKind::named("Bat").unwrap(),
+            context.player.position + Point::new(1, 0),
+            &mut context.rng,
+        );
+        enemy.health_points = 0;
+        enemy.experience_points = experience_for_level(3);
+        context.level.enemies.push(enemy);
+
+        context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        assert_eq!(context.player.experience_level, 3);
+        assert_eq!(
+            context
+                .events
+                .iter()
+                .filter(|event| matches!(event, Event::LeveledUp(_)))
+                .count(),
+            2
+        );
+    }
+    #[test]
+    fn gamecontext_hunger() {
+        let mut context = arena();
+        context.player.nutrition = HUNGRY_BELOW;
+        context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        assert!(matches!(
+            context.events[..],
+            [Event::HungerChanged(Hunger::Hungry)]
+        ));
+
+        // Weak players don't regenerate
+        context.player.nutrition = WEAK_BELOW;
+        context.player.health_points = 10;
+        for _ in 0..20 {
+            context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        }
+        assert_eq!(context.player.health_points, 10);
+
+        // Starving ones lose HP
+        context.player.nutrition = 1;
+        context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        assert_eq!(context.player.health_points, 9);
+        assert!(context
+            .events
+            .iter()
+            .any(|event| matches!(event, Event::HungerChanged(Hunger::Starving))));
+
+        context.player.inventory.push(PickUpItem::FoodRation);
+        context.next_turn(PlayerAction::UseItem(0));
+        assert_eq!(context.player.nutrition, FOOD_RATION_NUTRITION - 1);
+        assert!(context
+            .events
+            .iter()
+            .any(|event| matches!(event, Event::HungerChanged(Hunger::Fed))));
+
+        // Hunger goes by world time, so hasted players don't eat any faster
+        context.player.nutrition = MAX_NUTRITION;
+        context.player.statuses.add(StatusKind::Haste, 10);
+        for _ in 0..4 {
+            context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        }
+        assert_eq!(context.player.nutrition, MAX_NUTRITION - 2);
+    }
+    #[test]
+    fn gamecontext_regeneration() {
+        let mut context = arena();
+        context.player.health_points = 10;
+        for _ in 0..10 {
+            context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        }
+        assert_eq!(context.player.health_points, 11);
+
+        // Regeneration goes by world time too, so hasted players don't heal any faster
+        context.player.statuses.add(StatusKind::Haste, 20);
+        for _ in 0..20 {
+            context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        }
+        assert_eq!(context.player.health_points, 12);
+    }
+    #[test]
+    fn gamecontext_traps() {
+        let mut context = arena();
+        let start = context.player.position;
+        let dart_trap = start + Point::new(1, 0);
+        context.level.tiles[dart_trap] = Tile::Trap(Trap::hidden(TrapKind::Dart));
+        assert_eq!(context.level.tiles[dart_trap].as_seen(), Tile::Room);
+
+        context.next_turn(PlayerAction::MoveBy(Point::new(1, 0)));
+        assert!(matches!(
+            context.events[..2],
+            [Event::TriggeredTrap(TrapKind::Dart), Event::HitByDart(_)]
+        ));
+        assert!(context.player.health_points < context.player.max_health_points);
+        assert!(matches!(
+            context.level.tiles[dart_trap].as_seen(),
+            Tile::Trap(Trap { found: true, .. })
+        ));
+
+        // Searching finds the traps around
+        let teleport_trap = context.player.position + Point::new(2, 2);
+        context.level.tiles[teleport_trap] = Tile::Trap(Trap::hidden(TrapKind::Teleport));
+        context.next_turn(PlayerAction::Search);
+        assert!(matches!(
+            context.events[..],
+            [Event::FoundTrap(TrapKind::Teleport)]
+        ));
+        context.next_turn(PlayerAction::Search);
+        assert!(matches!(context.events[..], [Event::FoundNothing]));
+
+        // Trapdoors lead to the next level, away from its up stairs
+        context.level.tiles[context.player.position + Point::new(0, 1)] =
+            Tile::Trap(Trap::hidden(TrapKind::Trapdoor));
+        context.next_turn(PlayerAction::MoveBy(Point::new(0, 1)));
+        assert_eq!(context.current_level, 2);
+        assert_ne!(context.player.position, context.level.up_stairs);
+        assert_eq!(context.level.tiles[context.player.position], Tile::Room);
+
+        // Dropping something on a hidden trap sets it off rather than giving it away
+        context.level.tiles[context.player.position] = Tile::Trap(Trap::hidden(TrapKind::Alarm));
+        context.player.inventory = vec![PickUpItem::HealthBoost];
+        context.next_turn(PlayerAction::DropItem(0));
+        assert!(matches!(
+            context.events[0],
+            Event::TriggeredTrap(TrapKind::Alarm)
+        ));
+        assert!(!context
+            .events
+            .iter()
+            .any(|event| matches!(event, Event::CantDropHere)));
+    }
+    #[test]
+    fn gamecontext_last_level() {
+        let mut context = arena();
+        let level_count = dungeon_config().level_count;
+        assert!(!context.is_last_level());
+
+        // The down stairs of the last level lead out of the dungeon, winning the game
+        context.current_level = level_count;
+        assert!(context.is_last_level());
+        context.level.tiles[context.player.position] = Tile::Stairs(1);
+        context.next_turn(PlayerAction::Select);
+        assert!(matches!(context.events[..], [Event::Won(_)]));
+    }
+    #[test]
+    fn gamecontext_secret_doors() {
+        let mut context = arena();
+        let start = context.player.position;
+        let secret_door = start + Point::new(1, 0);
+        context.level.tiles[secret_door] = Tile::Door(DoorState::Secret);
+        assert_eq!(context.level.tiles[secret_door].as_seen(), Tile::Wall);
+
+        context.next_turn(PlayerAction::MoveBy(Point::new(1, 0)));
+        assert_eq!(context.player.position, start);
+        context.next_turn(PlayerAction::Search);
+        assert!(matches!(context.events[..], [Event::FoundSecretDoor]));
+        assert_eq!(
+            context.level.tiles[secret_door],
+            Tile::Door(DoorState::Closed)
+        );
+
+        // Magic mapping reveals them all
+        let far_door = start + Point::new(10, 0);
+        context.level.tiles[far_door] = Tile::Door(DoorState::Secret);
+        context.player.inventory = vec![PickUpItem::MagicMappingScroll];
+        context.next_turn(PlayerAction::UseItem(0));
+        assert_eq!(context.level.tiles[far_door], Tile::Door(DoorState::Closed));
+    }
+    #[test]
+    fn gamecontext_morale() {
+        let mut context = arena();
+        let mut enemy = Being::new_of_kind(
+            BeingKind::named("Gnoll").unwrap(),
+            context.player.position + Point::new(1, 0),
+            &mut context.rng,
+        );
+        enemy.health_points = 1;
+        context.level.enemies.push(enemy);
+
+        context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        assert!(context
+            .events
+            .iter()
+            .any(|event| matches!(event, Event::Fled(_))));
+        assert!(context.level.enemies[0].fleeing);
+        let distance = |context: &GameContext| {
+            context.level.enemies[0]
+                .position
+                .distance_to(context.player.position)
+        };
+        assert_eq!(distance(&context), 2);
+        context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        assert_eq!(distance(&context), 3);
+
+        // Once it regenerates, it comes back
+        context.level.enemies[0].health_points = context.level.enemies[0].max_health_points;
+        context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+        assert!(!context.level.enemies[0].fleeing);
+        assert_eq!(distance(&context), 2);
     }
 }
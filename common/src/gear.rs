@@ -0,0 +1,206 @@
+use std::fmt::Display;
+
+use rand::distributions::weighted::alias_method::WeightedIndex;
+use rand::prelude::Distribution;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// count dice with sides sides each, e.g. 2d4
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dice {
+    pub count: i32,
+    pub sides: i32,
+}
+impl Dice {
+    pub fn new(count: i32, sides: i32) -> Self {
+        Dice { count, sides }
+    }
+    pub fn roll(self, rng: &mut impl Rng) -> i32 {
+        (0..self.count)
+            .map(|_| rng.gen_range(1, self.sides + 1))
+            .sum()
+    }
+}
+impl Display for Dice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GearSlot {
+    Weapon,
+    Armour,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GearKind {
+    Dagger,
+    Sword,
+    Axe,
+    LeatherArmour,
+    ChainMail,
+    PlateArmour,
+}
+impl GearKind {
+    pub fn slot(self) -> GearSlot {
+        match self {
+            GearKind::Dagger | GearKind::Sword | GearKind::Axe => GearSlot::Weapon,
+            GearKind::LeatherArmour | GearKind::ChainMail | GearKind::PlateArmour => {
+                GearSlot::Armour
+            }
+        }
+    }
+    pub fn dice(self) -> Dice {
+        match self {
+            GearKind::Dagger => Dice::new(1, 4),
+            GearKind::Sword => Dice::new(1, 8),
+            GearKind::Axe => Dice::new(2, 6),
+            GearKind::LeatherArmour => Dice::new(1, 3),
+            GearKind::ChainMail => Dice::new(1, 6),
+            GearKind::PlateArmour => Dice::new(2, 5),
+        }
+    }
+}
+impl Display for GearKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                GearKind::Dagger => "Dagger",
+                GearKind::Sword => "Sword",
+                GearKind::Axe => "Axe",
+                GearKind::LeatherArmour => "Leather Armour",
+                GearKind::ChainMail => "Chain Mail",
+                GearKind::PlateArmour => "Plate Armour",
+            }
+        )
+    }
+}
+
+#[rustfmt::skip]
+pub const GEAR_KINDS: [GearKind; 6] = [
+    GearKind::Dagger,        GearKind::Sword,     GearKind::Axe,
+    GearKind::LeatherArmour, GearKind::ChainMail, GearKind::PlateArmour,
+];
+
+// A weapon or a piece of armour.
+// Its roll adds to the attack or the shielding of whoever has it equipped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Gear {
+    pub kind: GearKind,
+    pub bonus: i32,
+}
+impl Gear {
+    pub fn new(kind: GearKind, bonus: i32) -> Self {
+        Gear { kind, bonus }
+    }
+    // The deeper the level, the better the kinds and the bonuses of the generated gear
+    pub fn generate(level: i32, rng: &mut impl Rng) -> Self {
+        let basic_weight = (15 - level).clamp(1, 10);
+        let medium_weight = (level + 1).clamp(1, 10);
+        let heavy_weight = (level - 8).clamp(0, 10);
+        #[rustfmt::skip]
+        let kind_weights = vec![
+            basic_weight, medium_weight, heavy_weight,
+            basic_weight, medium_weight, heavy_weight,
+        ];
+        let kind_dist = WeightedIndex::new(kind_weights).unwrap();
+
+        Gear::new(
+            GEAR_KINDS[kind_dist.sample(rng)],
+            rng.gen_range(0, level / 6 + 2),
+        )
+    }
+    pub fn slot(self) -> GearSlot {
+        self.kind.slot()
+    }
+    pub fn roll(self, rng: &mut impl Rng) -> i32 {
+        self.kind.dice().roll(rng) + self.bonus
+    }
+}
+impl Display for Gear {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "+{} {} ({})", self.bonus, self.kind, self.kind.dice())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Equipment {
+    pub weapon: Option<Gear>,
+    pub armour: Option<Gear>,
+}
+impl Equipment {
+    pub fn get(&self, slot: GearSlot) -> Option<Gear> {
+        match slot {
+            GearSlot::Weapon => self.weapon,
+            GearSlot::Armour => self.armour,
+        }
+    }
+    // Puts the gear in its slot, returning what was there before
+    pub fn equip(&mut self, gear: Gear) -> Option<Gear> {
+        match gear.slot() {
+            GearSlot::Weapon => self.weapon.replace(gear),
+            GearSlot::Armour => self.armour.replace(gear),
+        }
+    }
+    pub fn unequip(&mut self, slot: GearSlot) -> Option<Gear> {
+        match slot {
+            GearSlot::Weapon => self.weapon.take(),
+            GearSlot::Armour => self.armour.take(),
+        }
+    }
+    pub fn roll(&self, slot: GearSlot, rng: &mut impl Rng) -> i32 {
+        self.get(slot).map_or(0, |gear| gear.roll(rng))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::GameRng;
+
+    #[test]
+    fn dice_roll() {
+        let mut rng = GameRng::seed_from_u64(0);
+        let dice = Dice::new(2, 6);
+        for _ in 0..100 {
+            let roll = dice.roll(&mut rng);
+            assert!((2..=12).contains(&roll));
+        }
+        assert_eq!(Dice::new(0, 6).roll(&mut rng), 0);
+    }
+    #[test]
+    fn gear_generate() {
+        let mut rng = GameRng::seed_from_u64(0);
+        for level in 1..=25 {
+            let gear = Gear::generate(level, &mut rng);
+            assert!(gear.bonus >= 0 && gear.bonus <= level / 6 + 1);
+        }
+        for _ in 0..100 {
+            assert_ne!(Gear::generate(1, &mut rng).kind, GearKind::Axe);
+        }
+    }
+    #[test]
+    fn equipment_equip() {
+        let mut equipment = Equipment::default();
+        let dagger = Gear::new(GearKind::Dagger, 0);
+        let sword = Gear::new(GearKind::Sword, 1);
+        let mail = Gear::new(GearKind::ChainMail, 0);
+
+        assert_eq!(equipment.equip(dagger), None);
+        assert_eq!(equipment.equip(mail), None);
+        assert_eq!(equipment.equip(sword), Some(dagger));
+        assert_eq!(equipment.get(GearSlot::Weapon), Some(sword));
+
+        assert_eq!(equipment.unequip(GearSlot::Armour), Some(mail));
+        assert_eq!(equipment.unequip(GearSlot::Armour), None);
+        assert_eq!(
+            equipment.roll(GearSlot::Armour, &mut GameRng::seed_from_u64(0)),
+            0
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{HUNGRY_BELOW, WEAK_BELOW};
+
+// How hungry the player is, depending on their nutrition
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Hunger {
+    Fed,
+    Hungry,
+    // Doesn't regenerate HP anymore
+    Weak,
+    // Loses 1 HP every turn
+    Starving,
+}
+impl Hunger {
+    pub fn of(nutrition: i32) -> Self {
+        if nutrition <= 0 {
+            Hunger::Starving
+        } else if nutrition < WEAK_BELOW {
+            Hunger::Weak
+        } else if nutrition < HUNGRY_BELOW {
+            Hunger::Hungry
+        } else {
+            Hunger::Fed
+        }
+    }
+}
+impl Display for Hunger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Hunger::Fed => "Not hungry",
+                Hunger::Hungry => "Hungry",
+                Hunger::Weak => "Weak",
+                Hunger::Starving => "Starving",
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hunger_of() {
+        assert_eq!(Hunger::of(HUNGRY_BELOW), Hunger::Fed);
+        assert_eq!(Hunger::of(HUNGRY_BELOW - 1), Hunger::Hungry);
+        assert_eq!(Hunger::of(WEAK_BELOW - 1), Hunger::Weak);
+        assert_eq!(Hunger::of(0), Hunger::Starving);
+        assert!(Hunger::Starving > Hunger::Hungry);
+    }
+}
@@ -1,17 +1,31 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
 use rand::distributions::weighted::alias_method::WeightedIndex;
 use rand::prelude::Distribution;
-use rand::{thread_rng, Rng};
-use std::cmp::{max, min};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::being::Being;
-use crate::beingkind::ENEMIES_KIND;
-use crate::pickupitem::PickUpItem;
+use crate::being::{Being, PackMember, PackRole};
+use crate::beingkind::BeingKind;
+use crate::dungeon::dungeon_config;
+use crate::gear::Gear;
+use crate::levelgen::Layout;
+use crate::monsterkind::monster_kinds;
+use crate::pickupitem::{PickUpItem, CONSUMABLES};
 use crate::point::Point;
 use crate::room::Room;
-use crate::tile::Tile;
+use crate::tile::{DoorState, Tile};
 use crate::tilearray::TileArray;
+use crate::trap::{Trap, TrapKind, TRAP_KINDS};
+use crate::validation::{validate, LevelProblem};
+use crate::vault::vaults;
+use crate::GameRng;
+
+// The cost of one step in Dijkstra maps
+const STEP_COST: i32 = 10;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Level {
     pub tiles: TileArray<Tile>,
     pub last_seen: TileArray<u32>,
@@ -20,85 +34,289 @@ pub struct Level {
     pub down_stairs: Point,
 }
 impl Level {
-    pub fn generate(level: i32) -> Self {
-        let mut rng = thread_rng();
-
-        let mut tiles = TileArray::new(Tile::Wall);
-
-        let first_room = Room::generate();
-        tiles.carve_room(first_room);
-
-        let mut prev_room = first_room;
-        for _ in 0..10 {
-            let new_room = Room::generate();
-            tiles.carve_room(new_room);
-            tiles.carve_corridor_between(prev_room, new_room);
-
-            prev_room = new_room;
+    // Builds the level at the given depth with the generator the dungeon configuration picks for it.
+    // What ends up out of reach gets cleared away, and if that's not enough, the level is built anew.
+    pub fn generate(level: i32, rng: &mut GameRng) -> Self {
+        let config = dungeon_config();
+        let generator = config.generator_for(level).generator();
+        loop {
+            let mut generated = generator.generate(level, config.dimensions, rng);
+            match validate(&generated) {
+                Ok(()) => break generated,
+                Err(problems) if problems.iter().all(|problem| problem.is_repairable()) => {
+                    generated.repair(&problems);
+                    break generated;
+                }
+                Err(_) => {}
+            }
         }
-
-        let last_room = Room::generate_not_overlapping(first_room);
-        tiles.carve_room(last_room);
-        tiles.carve_corridor_between(prev_room, last_room);
-
-        let up_stairs = first_room.generate_inner_point();
+    }
+    fn repair(&mut self, problems: &[LevelProblem]) {
+        for &problem in problems {
+            match problem {
+                LevelProblem::UnreachableItem(point) => self.tiles[point] = Tile::Room,
+                LevelProblem::UnreachableEnemy(point) => {
+                    self.enemies.retain(|enemy| enemy.position != point)
+                }
+                _ => {}
+            }
+        }
+    }
+    // Puts the stairs, the doors, the items, the traps and the enemies fitting the depth
+    // in a carved out level
+    pub fn furnish(level: i32, layout: Layout, rng: &mut impl Rng) -> Self {
+        let Layout {
+            mut tiles,
+            rooms,
+            up_stairs,
+            down_stairs,
+        } = layout;
+        let (width, height) = (tiles.width(), tiles.height());
         tiles[up_stairs] = Tile::Stairs(-1);
-
-        let down_stairs = last_room.generate_inner_point();
         tiles[down_stairs] = Tile::Stairs(1);
 
         for _ in 0..50 {
-            let proposed_point = tiles.generate_floor_point();
+            let proposed_point = tiles.generate_floor_point(rng);
             if tiles.is_pillar_worthy(proposed_point) {
                 tiles[proposed_point] = Tile::Wall;
             } else if tiles.is_door_worthy(proposed_point) {
-                tiles[proposed_point] = Tile::Door;
+                tiles[proposed_point] = Tile::Door(if rng.gen_bool(0.1) {
+                    DoorState::Secret
+                } else if rng.gen_bool(0.5) {
+                    DoorState::Closed
+                } else {
+                    DoorState::Open
+                });
             }
         }
 
+        // Secret doors can't be the only way down
+        for door in tiles.secret_doors_between(up_stairs, down_stairs) {
+            tiles[door] = Tile::Door(DoorState::Closed);
+        }
+
+        // Some levels hold a vault, guarded by the monsters drawn in it
+        let mut enemies = vec![];
+        if rng.gen_range(0, 100) < dungeon_config().vault_chance(level) {
+            let fitting: Vec<_> = vaults()
+                .iter()
+                .filter(|vault| (vault.depth.0..=vault.depth.1).contains(&level))
+                .collect();
+            if let Some(vault) = fitting.choose(rng) {
+                if let Some(guards) = vault.stamp(&mut tiles, level, rng) {
+                    enemies = guards;
+                }
+            }
+        }
+
+        // A treasure room hidden behind a secret door rewards the thorough players
+        if rng.gen_bool(0.4) {
+            if let Some(room) =
+                tiles.carve_hidden_room(dungeon_config().dimensions.min_room_width, rng)
+            {
+                for _ in 0..rng.gen_range(2, 4) {
+                    let item_point = room.generate_inner_point(rng);
+                    tiles[item_point] = Tile::Item(if rng.gen_bool(0.5) {
+                        PickUpItem::Gear(Gear::generate(level + 2, rng))
+                    } else {
+                        CONSUMABLES[rng.gen_range(0, CONSUMABLES.len())]
+                    });
+                }
+            }
+        }
+
+        // Some of the closed doors are locked, each with a key the player can get to without it
+        let mut locked_doors = 0;
+        for y in 0..height {
+            for x in 0..width {
+                if tiles[(y, x)] == Tile::Door(DoorState::Closed) && rng.gen_bool(0.2) {
+                    tiles[(y, x)] = Tile::Door(DoorState::Locked);
+                    locked_doors += 1;
+                }
+            }
+        }
+        let key_points: Vec<Point> = tiles
+            .reachable_from(up_stairs)
+            .into_iter()
+            .filter(|&point| tiles[point] == Tile::Room)
+            .collect();
+        for &key_point in key_points.choose_multiple(rng, locked_doors) {
+            tiles[key_point] = Tile::Item(PickUpItem::Key);
+        }
+
         if rng.gen_bool(0.5) {
-            let item_point = tiles.generate_floor_point();
+            let item_point = tiles.generate_floor_point(rng);
             tiles[item_point] = Tile::Item(PickUpItem::HealthBoost);
         }
         if rng.gen_bool(0.4) {
-            let item_point = tiles.generate_floor_point();
+            let item_point = tiles.generate_floor_point(rng);
             tiles[item_point] = Tile::Item(PickUpItem::AttackBoost);
         }
         if rng.gen_bool(0.4) {
-            let item_point = tiles.generate_floor_point();
+            let item_point = tiles.generate_floor_point(rng);
             tiles[item_point] = Tile::Item(PickUpItem::ShieldBoost);
         }
 
-        let mut enemies = vec![];
-        let tier_1_enemy_weight = max(0, min(level + 1, 12 - level));
-        let tier_2_enemy_weight = max(0, min(level - 4, 20 - level));
-        let tier_3_enemy_weight = max(0, min(level - 12, 26 - level));
+        if rng.gen_bool(0.35) {
+            let item_point = tiles.generate_floor_point(rng);
+            tiles[item_point] = Tile::Item(PickUpItem::Gear(Gear::generate(level, rng)));
+        }
+        if rng.gen_bool(0.6) {
+            let item_point = tiles.generate_floor_point(rng);
+            tiles[item_point] = Tile::Item(PickUpItem::FoodRation);
+        }
+
         #[rustfmt::skip]
-        let enemy_weights = vec![
-            tier_1_enemy_weight * 5, tier_1_enemy_weight * 3, tier_1_enemy_weight * 2,
-            tier_2_enemy_weight * 5, tier_2_enemy_weight * 3, tier_2_enemy_weight * 2,
-            tier_3_enemy_weight * 5, tier_3_enemy_weight * 3, tier_3_enemy_weight * 2,
+        let consumable_weights = vec![
+            6, level, 1 + level / 5,
+            2,
+            2 + level / 4, 1 + level / 3, 1 + level / 5,
         ];
-        let enemy_dist = WeightedIndex::new(enemy_weights).unwrap();
-        for _ in 0..(6 + level / 2) {
-            let enemy_position = tiles.generate_floor_point();
-            if enemies
-                .iter()
-                .all(|enemy: &Being| enemy.position != enemy_position)
-            {
-                enemies.push(Being::new_of_kind(
-                    ENEMIES_KIND[enemy_dist.sample(&mut rng)],
-                    enemy_position,
-                ));
+        let consumable_dist = WeightedIndex::new(consumable_weights).unwrap();
+        for _ in 0..rng.gen_range(1, 3) {
+            let item_point = tiles.generate_floor_point(rng);
+            tiles[item_point] = Tile::Item(CONSUMABLES[consumable_dist.sample(rng)]);
+        }
+
+        let trap_kinds: Vec<TrapKind> = TRAP_KINDS
+            .into_iter()
+            .filter(|kind| kind.allowed_on(level))
+            .collect();
+        for _ in 0..rng.gen_range(1, 3 + level / 5) {
+            let trap_point = tiles.generate_floor_point(rng);
+            tiles[trap_point] =
+                Tile::Trap(Trap::hidden(trap_kinds[rng.gen_range(0, trap_kinds.len())]));
+        }
+
+        let enemy_weights: Vec<i32> = monster_kinds()
+            .iter()
+            .map(|kind| kind.weight(level))
+            .collect();
+        // Custom monster definitions might leave a level without any monster
+        if let Ok(enemy_dist) = WeightedIndex::new(enemy_weights) {
+            let mut spawned = 0;
+            let mut pack_count = 0;
+            while spawned < 6 + level / 2 {
+                let kind = BeingKind::Monster(enemy_dist.sample(rng));
+                let pack_size = kind.monster().map_or((1, 1), |monster| monster.pack_size);
+                let size = rng.gen_range(pack_size.0, pack_size.1 + 1);
+
+                if size == 1 {
+                    let enemy_position = tiles.generate_floor_point(rng);
+                    if enemies
+                        .iter()
+                        .all(|enemy: &Being| enemy.position != enemy_position)
+                    {
+                        enemies.push(Being::new_of_kind(kind, enemy_position, rng));
+                    }
+                } else {
+                    // The pack is spawned together in a room other than the one with the up stairs,
+                    // or around some spot of a level without rooms
+                    let room = if rooms.len() > 1 {
+                        rooms[rng.gen_range(1, rooms.len())]
+                    } else {
+                        let center = tiles.generate_floor_point(rng);
+                        Room::new(center.x - 2, center.y - 2, 4, 4)
+                    };
+                    let mut member = 0;
+                    for _ in 0..size {
+                        let enemy_position = room.generate_inner_point(rng);
+                        if tiles[enemy_position] == Tile::Room
+                            && enemies
+                                .iter()
+                                .all(|enemy: &Being| enemy.position != enemy_position)
+                        {
+                            let mut enemy = Being::new_of_kind(kind, enemy_position, rng);
+                            enemy.pack = Some(PackMember {
+                                pack: pack_count,
+                                member,
+                                role: if member == 0 {
+                                    PackRole::Leader
+                                } else {
+                                    PackRole::Follower
+                                },
+                            });
+                            enemies.push(enemy);
+                            member += 1;
+                        }
+                    }
+                    pack_count += 1;
+                }
+
+                spawned += size;
             }
         }
 
         Level {
             tiles,
-            last_seen: TileArray::new(0),
+            last_seen: TileArray::new(width, height, 0),
             enemies,
             up_stairs,
             down_stairs,
         }
     }
+    // Whether a being can step (or strike) from a point to a neighboring one.
+    // Doors can only be walked through straight, never cut diagonally.
+    pub fn can_step(&self, from: Point, to: Point) -> bool {
+        self.tiles.in_bounds(to)
+            && self.tiles[to].is_walkable()
+            && (!from.is_diagonal_to(to)
+                || (!self.tiles[from].is_door() && !self.tiles[to].is_door()))
+    }
+    // How far every tile is from the closest source, starting from the source's own value.
+    // Every step costs STEP_COST, unreachable tiles are left at i32::MAX.
+    pub fn dijkstra_map(&self, sources: impl IntoIterator<Item = (Point, i32)>) -> TileArray<i32> {
+        let mut map = TileArray::new(self.tiles.width(), self.tiles.height(), i32::MAX);
+        let mut queue = BinaryHeap::new();
+        for (point, value) in sources {
+            map[point] = value;
+            queue.push((Reverse(value), point.y, point.x));
+        }
+
+        while let Some((Reverse(value), y, x)) = queue.pop() {
+            let point = Point::new(x, y);
+            if value > map[point] {
+                continue;
+            }
+            for neighbor in point.iter_neighbors() {
+                if self.can_step(point, neighbor) && value + STEP_COST < map[neighbor] {
+                    map[neighbor] = value + STEP_COST;
+                    queue.push((Reverse(value + STEP_COST), neighbor.y, neighbor.x));
+                }
+            }
+        }
+
+        map
+    }
+    // Where to run to get away from the player at `from`: the lower, the safer.
+    // Just getting as far as possible from the player would leave monsters stuck in corners,
+    // so the distances are flipped and scaled past zero, then smoothed again.
+    // This makes monsters prefer slipping past the player to reach open areas over dead ends.
+    pub fn flee_map(&self, from: Point) -> TileArray<i32> {
+        let distances = self.dijkstra_map([(from, 0)]);
+        let mut sources = vec![];
+        for point in distances.points() {
+            if distances[point] < i32::MAX {
+                sources.push((point, -distances[point] * 6 / 5));
+            }
+        }
+
+        self.dijkstra_map(sources)
+    }
+    // The tiles a projectile flies over on its way from `from` to `to`.
+    // It stops before blocking tiles, and on the first enemy it meets.
+    pub fn projectile_path(&self, from: Point, to: Point, range: i32) -> Vec<Point> {
+        let mut path = vec![];
+        for point in from.line_to(to).into_iter().take(range as usize) {
+            if !self.tiles.in_bounds(point) || self.tiles[point].is_blocking() {
+                break;
+            }
+            path.push(point);
+            if self.enemies.iter().any(|enemy| enemy.position == point) {
+                break;
+            }
+        }
+
+        path
+    }
 }
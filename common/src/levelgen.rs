@@ -0,0 +1,300 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dungeon::Dimensions, level::Level, point::Point, room::Room, tile::Tile, tilearray::TileArray,
+    GameRng,
+};
+
+// The shape of a level, before anything gets put in it
+pub struct Layout {
+    pub tiles: TileArray<Tile>,
+    // Where the packs of monsters gather; caves have no rooms, so the packs gather anywhere
+    pub rooms: Vec<Room>,
+    pub up_stairs: Point,
+    pub down_stairs: Point,
+}
+
+pub trait LevelGenerator {
+    // Carves out the walls and the floor of a level of the given size
+    fn carve(&self, dimensions: Dimensions, rng: &mut GameRng) -> Layout;
+    // Builds the whole level at the given depth
+    fn generate(&self, level: i32, dimensions: Dimensions, rng: &mut GameRng) -> Level {
+        let layout = self.carve(dimensions, rng);
+        Level::furnish(level, layout, rng)
+    }
+}
+
+// The level generators the dungeon configuration can pick from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GeneratorKind {
+    RoomsAndCorridors,
+    Bsp,
+    Caves,
+}
+impl GeneratorKind {
+    pub fn generator(self) -> &'static dyn LevelGenerator {
+        match self {
+            GeneratorKind::RoomsAndCorridors => &RoomsAndCorridors,
+            GeneratorKind::Bsp => &Bsp,
+            GeneratorKind::Caves => &Caves,
+        }
+    }
+}
+
+// Random rooms, each joined to the previous one by a corridor.
+// The up stairs are in the first room, and the down stairs in the last one, away from it.
+pub struct RoomsAndCorridors;
+impl LevelGenerator for RoomsAndCorridors {
+    fn carve(&self, dimensions: Dimensions, rng: &mut GameRng) -> Layout {
+        let mut tiles = TileArray::new(dimensions.width, dimensions.height, Tile::Wall);
+
+        let first_room = Room::generate(dimensions, rng);
+        tiles.carve_room(first_room);
+
+        let mut rooms = vec![first_room];
+        for _ in 0..10 {
+            let new_room = Room::generate(dimensions, rng);
+            tiles.carve_room(new_room);
+            tiles.carve_corridor_between(rooms[rooms.len() - 1], new_room, rng);
+
+            rooms.push(new_room);
+        }
+
+        let last_room = Room::generate_not_overlapping(first_room, dimensions, rng);
+        tiles.carve_room(last_room);
+        tiles.carve_corridor_between(rooms[rooms.len() - 1], last_room, rng);
+        rooms.push(last_room);
+
+        let up_stairs = first_room.generate_inner_point(rng);
+        let down_stairs = last_room.generate_inner_point(rng);
+
+        Layout {
+            tiles,
+            rooms,
+            up_stairs,
+            down_stairs,
+        }
+    }
+}
+
+// The level gets split in two again and again, until the pieces are about the size of a room.
+// Every piece gets a room, and every two halves get joined by a corridor,
+// so the rooms are spread evenly and never overlap.
+pub struct Bsp;
+impl Bsp {
+    // Returns the rooms carved in the area, from its top left to its bottom right
+    fn split(
+        area: Room,
+        dimensions: Dimensions,
+        tiles: &mut TileArray<Tile>,
+        rng: &mut GameRng,
+    ) -> Vec<Room> {
+        let (min_room_width, max_room_width) =
+            (dimensions.min_room_width, dimensions.max_room_width);
+        // The smallest piece of the level a room fits in
+        let min_area = min_room_width + 2;
+        let width = area.bottom_right.x - area.top_left.x;
+        let height = area.bottom_right.y - area.top_left.y;
+        let can_split_x = width >= 2 * min_area;
+        let can_split_y = height >= 2 * min_area;
+        let is_small = width <= max_room_width + 2 && height <= max_room_width + 2;
+
+        if !(can_split_x || can_split_y) || (is_small && rng.gen_bool(0.5)) {
+            let room_width = rng.gen_range(min_room_width, (width + 1).min(max_room_width));
+            let room_height = rng.gen_range(min_room_width, (height + 1).min(max_room_width));
+            let room = Room::new(
+                rng.gen_range(area.top_left.x, area.bottom_right.x - room_width + 1),
+                rng.gen_range(area.top_left.y, area.bottom_right.y - room_height + 1),
+                room_width,
+                room_height,
+            );
+            tiles.carve_room(room);
+            return vec![room];
+        }
+
+        // The longer side gets split, so the pieces don't get too thin
+        let (first, second) = if can_split_x && (!can_split_y || width > height) {
+            let split = rng.gen_range(min_area, width - min_area + 1);
+            (
+                Room::new(area.top_left.x, area.top_left.y, split, height),
+                Room::new(
+                    area.top_left.x + split,
+                    area.top_left.y,
+                    width - split,
+                    height,
+                ),
+            )
+        } else {
+            let split = rng.gen_range(min_area, height - min_area + 1);
+            (
+                Room::new(area.top_left.x, area.top_left.y, width, split),
+                Room::new(
+                    area.top_left.x,
+                    area.top_left.y + split,
+                    width,
+                    height - split,
+                ),
+            )
+        };
+        let mut rooms = Bsp::split(first, dimensions, tiles, rng);
+        let second_rooms = Bsp::split(second, dimensions, tiles, rng);
+        let from = rooms[rng.gen_range(0, rooms.len())];
+        let to = second_rooms[rng.gen_range(0, second_rooms.len())];
+        tiles.carve_corridor_between(from, to, rng);
+        rooms.extend(second_rooms);
+
+        rooms
+    }
+}
+impl LevelGenerator for Bsp {
+    fn carve(&self, dimensions: Dimensions, rng: &mut GameRng) -> Layout {
+        let mut tiles = TileArray::new(dimensions.width, dimensions.height, Tile::Wall);
+        let whole_level = Room::new(0, 0, dimensions.width - 1, dimensions.height - 1);
+        let rooms = Bsp::split(whole_level, dimensions, &mut tiles, rng);
+
+        let up_stairs = rooms[0].generate_inner_point(rng);
+        let down_stairs = rooms[rooms.len() - 1].generate_inner_point(rng);
+
+        Layout {
+            tiles,
+            rooms,
+            up_stairs,
+            down_stairs,
+        }
+    }
+}
+
+// Natural caves grown by a cellular automaton: starting from random noise,
+// every tile turns into rock when most of its neighbors are rock, and into floor otherwise.
+// Only the biggest cave is kept, with the stairs as far apart as its passages allow.
+pub struct Caves;
+impl LevelGenerator for Caves {
+    fn carve(&self, dimensions: Dimensions, rng: &mut GameRng) -> Layout {
+        let (width, height) = (dimensions.width, dimensions.height);
+        loop {
+            let mut tiles = TileArray::new(width, height, Tile::Wall);
+            for y in 1..(height - 1) {
+                for x in 1..(width - 1) {
+                    if rng.gen_bool(0.55) {
+                        tiles[(y, x)] = Tile::Room;
+                    }
+                }
+            }
+            for _ in 0..4 {
+                let previous = tiles.clone();
+                for y in 1..(height - 1) {
+                    for x in 1..(width - 1) {
+                        let walls = Point::new(x, y)
+                            .iter_neighbors()
+                            .filter(|&p| previous[p] == Tile::Wall)
+                            .count();
+                        if walls >= 5 {
+                            tiles[(y, x)] = Tile::Wall;
+                        } else if walls <= 3 {
+                            tiles[(y, x)] = Tile::Room;
+                        }
+                    }
+                }
+            }
+
+            let mut visited = TileArray::new(width, height, false);
+            let mut biggest_cave: Vec<Point> = vec![];
+            for point in tiles.points() {
+                if tiles[point] == Tile::Room && !visited[point] {
+                    let cave = tiles.reachable_from(point);
+                    for &point in cave.iter() {
+                        visited[point] = true;
+                    }
+                    if cave.len() > biggest_cave.len() {
+                        biggest_cave = cave;
+                    }
+                }
+            }
+            // Too small a cave makes for a dull level, so it gets grown again
+            if (biggest_cave.len() as i32) < width * height / 3 {
+                continue;
+            }
+
+            let mut cave_tiles = TileArray::new(width, height, Tile::Wall);
+            for &point in biggest_cave.iter() {
+                cave_tiles[point] = Tile::Room;
+            }
+            let up_stairs = biggest_cave[rng.gen_range(0, biggest_cave.len())];
+            // The tiles are reached in the order of their distance, so the last one is the furthest
+            let down_stairs = *cave_tiles.reachable_from(up_stairs).last().unwrap();
+
+            break Layout {
+                tiles: cave_tiles,
+                rooms: vec![],
+                up_stairs,
+                down_stairs,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::dungeon::dungeon_config;
+
+    // The smallest levels the dungeon configuration allows with rooms this wide
+    const TINY: Dimensions = Dimensions {
+        width: 12,
+        height: 12,
+        min_room_width: 3,
+        max_room_width: 6,
+    };
+
+    #[test]
+    fn levelgen_layouts() {
+        let mut rng = GameRng::seed_from_u64(0);
+        for kind in [
+            GeneratorKind::RoomsAndCorridors,
+            GeneratorKind::Bsp,
+            GeneratorKind::Caves,
+        ] {
+            for dimensions in [dungeon_config().dimensions, TINY].repeat(10) {
+                let layout = kind.generator().carve(dimensions, &mut rng);
+                assert_eq!(layout.tiles.width(), dimensions.width);
+                assert_eq!(layout.tiles.height(), dimensions.height);
+                assert_eq!(layout.tiles[layout.up_stairs], Tile::Room);
+                assert_eq!(layout.tiles[layout.down_stairs], Tile::Room);
+                assert_ne!(layout.up_stairs, layout.down_stairs);
+                assert!(layout
+                    .tiles
+                    .reachable_from(layout.up_stairs)
+                    .contains(&layout.down_stairs));
+            }
+        }
+    }
+    #[test]
+    fn levelgen_bsp_rooms() {
+        let mut rng = GameRng::seed_from_u64(0);
+        for _ in 0..20 {
+            let layout = Bsp.carve(dungeon_config().dimensions, &mut rng);
+            assert!(layout.rooms.len() >= 4);
+            for (i, room) in layout.rooms.iter().enumerate() {
+                assert!(layout.tiles.in_bounds(room.top_left));
+                assert!(layout.tiles.in_bounds(room.bottom_right));
+                assert!(layout.rooms[..i].iter().all(|other| !other.overlaps(*room)));
+            }
+        }
+    }
+    #[test]
+    fn levelgen_generate() {
+        let mut rng = GameRng::seed_from_u64(0);
+        for kind in [GeneratorKind::Bsp, GeneratorKind::Caves] {
+            for dimensions in [dungeon_config().dimensions, TINY] {
+                let level = kind.generator().generate(20, dimensions, &mut rng);
+                assert_eq!(level.tiles[level.up_stairs], Tile::Stairs(-1));
+                assert_eq!(level.tiles[level.down_stairs], Tile::Stairs(1));
+                assert_eq!(level.last_seen.width(), dimensions.width);
+                assert!(!level.enemies.is_empty());
+            }
+        }
+    }
+}
@@ -1,17 +1,63 @@
-pub const LEVEL_WIDTH: i32 = 60;
-pub const LEVEL_HEIGHT: i32 = 24;
-pub const LEVEL_COUNT: i32 = 25;
-pub const MIN_ROOM_WIDTH: i32 = 4;
-pub const MAX_ROOM_WIDTH: i32 = 10;
+pub const INVENTORY_SIZE: usize = 9;
+pub const THROW_RANGE: i32 = 8;
+pub const BLINK_RANGE: i32 = 6;
+pub const HEALING_POTION_HP: i32 = 10;
+pub const FEAR_DURATION: u32 = 10;
+// For how many turns hunting monsters look around the place they last saw the player,
+// and how far from it
+pub const SEARCH_DURATION: u32 = 5;
+pub const SEARCH_RADIUS: u32 = 2;
+// How far pack followers stray from their leader
+pub const PACK_RADIUS: u32 = 2;
+// A being gains its speed in energy every tick, and spends this much on every action
+pub const NORMAL_SPEED: i32 = 100;
+pub const ACTION_COST: i32 = 100;
+// How long the status effects of the potions last, and how much poison can pile up
+pub const HASTE_DURATION: u32 = 10;
+pub const REGENERATION_DURATION: u32 = 15;
+pub const MAX_POISON_TURNS: u32 = 20;
+// How far a blind being can see
+pub const BLIND_SIGHT_RADIUS: u32 = 1;
+// How many max HP the player gains on every level-up
+pub const LEVEL_UP_HP: i32 = 3;
+// The player's nutrition goes down by 1 every turn, and they get hungry, then weak below these
+pub const MAX_NUTRITION: i32 = 1500;
+pub const HUNGRY_BELOW: i32 = 300;
+pub const WEAK_BELOW: i32 = 100;
+pub const FOOD_RATION_NUTRITION: i32 = 800;
+// How far searching finds hidden things, and the percentage chance of noticing
+// a hidden trap next to the player without searching
+pub const PLAYER_SEARCH_RADIUS: u32 = 2;
+pub const PERCEPTION_CHANCE: i32 = 25;
+// How long the corridors dug to hidden rooms and vaults from the rest of the level can get
+pub const MAX_SIDE_CORRIDOR_LENGTH: usize = 12;
 
+// The RNG every random decision of the game is drawn from.
+// It is seedable, so that the same seed and the same actions always produce the same game.
+pub type GameRng = rand_pcg::Pcg32;
+
+pub mod behavior;
 pub mod being;
 pub mod beingkind;
+pub mod datafile;
+pub mod dungeon;
 pub mod event;
 pub mod gamecontext;
+pub mod gear;
+pub mod hunger;
 pub mod level;
+pub mod levelgen;
+pub mod monsterkind;
 pub mod pickupitem;
 pub mod playeraction;
 pub mod point;
+pub mod replay;
 pub mod room;
+pub mod savefile;
+pub mod status;
+pub mod targeting;
 pub mod tile;
 pub mod tilearray;
+pub mod trap;
+pub mod validation;
+pub mod vault;
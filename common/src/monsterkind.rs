@@ -0,0 +1,229 @@
+use std::{
+    cmp::{max, min},
+    fs,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    being::Being,
+    datafile::{DataError, DataFile},
+    status::StatusKind,
+    NORMAL_SPEED,
+};
+
+// The monster definitions the game ships with
+const DEFAULT_MONSTERS: &str = include_str!("../data/monsters.ron");
+// If a file with this name is found next to the game, its definitions replace the default ones
+pub const MONSTERS_FILE_NAME: &str = "monsters.ron";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Colour {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+#[rustfmt::skip]
+pub const COLOURS: [Colour; 7] = [
+    Colour::Red,     Colour::Green, Colour::Yellow, Colour::Blue,
+    Colour::Magenta, Colour::Cyan,  Colour::White,
+];
+
+// How a monster decides what to do on its turn
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AiKind {
+    // Chases the player when in sight
+    Chaser,
+    // Moves around randomly
+    Wanderer,
+    // Stays in place
+    Static,
+    // Attacks from a distance, keeping the player at its preferred range
+    Ranged,
+}
+
+// A status effect the monster's hits can put on the player
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Infliction {
+    pub status: StatusKind,
+    pub turns: u32,
+    // The percentage of the hits that inflict it
+    pub chance: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MonsterKind {
+    pub name: String,
+    pub glyph: char,
+    pub colour: Colour,
+    pub hp: i32,
+    // (min, max) XP given for killing the monster
+    pub xp: (i32, i32),
+    pub attack: i32,
+    pub shield: i32,
+    pub ai: AiKind,
+    #[serde(default = "normal_speed")]
+    pub speed: i32,
+    // (first, last) level the monster can appear on
+    pub depth: (i32, i32),
+    // How common the monster is compared to the others at the same depth
+    #[serde(default = "one")]
+    pub frequency: i32,
+    // Below this percentage of its HP the monster flees, 0 for monsters that never do
+    #[serde(default)]
+    pub flee_below: i32,
+    // and it comes back to fight once it regenerates above this percentage
+    #[serde(default = "sixty")]
+    pub return_above: i32,
+    // (min, max) number of monsters spawned together as a pack
+    #[serde(default = "alone")]
+    pub pack_size: (i32, i32),
+    // How far a Ranged monster attacks from,
+    #[serde(default)]
+    pub range: u32,
+    // and how close it lets the player get before backing off
+    #[serde(default)]
+    pub preferred_range: u32,
+    #[serde(default)]
+    pub inflicts: Option<Infliction>,
+    // Whether it can open closed doors
+    #[serde(default)]
+    pub opens_doors: bool,
+}
+impl MonsterKind {
+    pub fn breaks_morale(&self, being: &Being) -> bool {
+        being.health_points * 100 < being.max_health_points * self.flee_below
+    }
+    pub fn regains_morale(&self, being: &Being) -> bool {
+        being.health_points * 100 >= being.max_health_points * self.return_above
+    }
+    // The monster is the most common in the middle of its depth range
+    pub fn weight(&self, level: i32) -> i32 {
+        self.frequency * max(0, min(level - self.depth.0 + 1, self.depth.1 - level + 1))
+    }
+    fn validate(&self) -> Result<(), DataError> {
+        let problem = if self.hp <= 0 {
+            "its HP must be positive"
+        } else if self.xp.0 > self.xp.1 {
+            "its XP range is empty"
+        } else if self.attack < 0 {
+            "its attack can't be negative"
+        } else if self.shield <= 0 {
+            "its shield must be positive"
+        } else if self.speed <= 0 {
+            "its speed must be positive"
+        } else if self.depth.0 > self.depth.1 {
+            "its depth range is empty"
+        } else if self.frequency < 0 {
+            "its frequency can't be negative"
+        } else if !(0..=100).contains(&self.flee_below) || self.return_above > 100 {
+            "its morale thresholds must be percentages"
+        } else if self.return_above <= self.flee_below {
+            "it must come back above the HP it flees below"
+        } else if self.pack_size.0 < 1 || self.pack_size.0 > self.pack_size.1 {
+            "its pack size range is invalid"
+        } else if self.ai == AiKind::Ranged
+            && (self.preferred_range < 2 || self.preferred_range > self.range)
+        {
+            "it must prefer a distance of at least 2 within its range"
+        } else if self
+            .inflicts
+            .is_some_and(|infliction| infliction.turns == 0 || infliction.chance > 100)
+        {
+            "its infliction must last and have a percentage chance"
+        } else {
+            return Ok(());
+        };
+
+        Err(DataError::Invalid(format!(
+            "monster {}: {}",
+            self.name, problem
+        )))
+    }
+}
+fn normal_speed() -> i32 {
+    NORMAL_SPEED
+}
+fn one() -> i32 {
+    1
+}
+fn sixty() -> i32 {
+    60
+}
+fn alone() -> (i32, i32) {
+    (1, 1)
+}
+
+pub fn parse(source: &str) -> Result<Vec<MonsterKind>, DataError> {
+    let kinds: Vec<MonsterKind> = ron::from_str(source)?;
+    if kinds.is_empty() {
+        return Err(DataError::Invalid("monster list: it's empty".to_string()));
+    }
+    for (i, kind) in kinds.iter().enumerate() {
+        kind.validate()?;
+        if kinds[..i].iter().any(|other| other.name == kind.name) {
+            return Err(DataError::Invalid(format!(
+                "monster {}: it's defined twice",
+                kind.name
+            )));
+        }
+    }
+
+    Ok(kinds)
+}
+pub static MONSTER_KINDS: DataFile<Vec<MonsterKind>> = DataFile::new(
+    MONSTERS_FILE_NAME,
+    || parse(DEFAULT_MONSTERS).unwrap(),
+    |path| parse(&fs::read_to_string(path)?),
+);
+pub fn monster_kinds() -> &'static [MonsterKind] {
+    MONSTER_KINDS.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monsterkind_default_monsters() {
+        let kinds = parse(DEFAULT_MONSTERS).unwrap();
+        assert_eq!(kinds.len(), 11);
+        for level in 1..=25 {
+            assert!(kinds.iter().any(|kind| kind.weight(level) > 0));
+        }
+        assert_eq!(kinds[0].speed, NORMAL_SPEED);
+    }
+    #[test]
+    fn monsterkind_weight() {
+        let kind = &parse(DEFAULT_MONSTERS).unwrap()[3];
+        assert_eq!(kind.weight(4), 0);
+        assert_eq!(kind.weight(5), kind.frequency);
+        assert_eq!(kind.weight(12), kind.frequency * 8);
+        assert_eq!(kind.weight(19), kind.frequency);
+        assert_eq!(kind.weight(20), 0);
+    }
+    #[test]
+    fn monsterkind_invalid() {
+        let monster = r#"(name: "Rat", glyph: 'r', colour: White, hp: 3, xp: (1, 2),
+            attack: 1, shield: 1, ai: Wanderer, depth: (1, 5))"#;
+
+        assert!(parse(&format!("[{}]", monster)).is_ok());
+        assert!(matches!(parse("[]"), Err(DataError::Invalid(_))));
+        assert!(matches!(
+            parse(&format!("[{}, {}]", monster, monster)),
+            Err(DataError::Invalid(_))
+        ));
+        assert!(matches!(
+            parse(&format!("[{}]", monster.replace("hp: 3", "hp: 0"))),
+            Err(DataError::Invalid(_))
+        ));
+        assert!(matches!(
+            parse(&format!("[{}]", monster.replace("Wanderer", "Flyer"))),
+            Err(DataError::Format(_))
+        ));
+    }
+}
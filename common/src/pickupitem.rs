@@ -1,10 +1,25 @@
 use std::fmt::Display;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+
+use crate::gear::Gear;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PickUpItem {
     HealthBoost,
     AttackBoost,
     ShieldBoost,
+    HealingPotion,
+    BlinkPotion,
+    SpeedPotion,
+    RegenerationPotion,
+    TeleportScroll,
+    MagicMappingScroll,
+    FearScroll,
+    FoodRation,
+    // Unlocks a locked door, getting used up
+    Key,
+    Gear(Gear),
 }
 impl Display for PickUpItem {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -15,7 +30,25 @@ impl Display for PickUpItem {
                 PickUpItem::HealthBoost => "Health Boost",
                 PickUpItem::AttackBoost => "Attack Boost",
                 PickUpItem::ShieldBoost => "Shield Boost",
+                PickUpItem::HealingPotion => "Healing Potion",
+                PickUpItem::BlinkPotion => "Blink Potion",
+                PickUpItem::SpeedPotion => "Potion of Speed",
+                PickUpItem::RegenerationPotion => "Potion of Regeneration",
+                PickUpItem::TeleportScroll => "Teleport Scroll",
+                PickUpItem::MagicMappingScroll => "Magic Mapping Scroll",
+                PickUpItem::FearScroll => "Scroll of Fear",
+                PickUpItem::FoodRation => "Food Ration",
+                PickUpItem::Key => "Key",
+                PickUpItem::Gear(gear) => return write!(f, "{}", gear),
             }
         )
     }
 }
+
+// Items that get used up in one go, with an effect more immediate than the one of the boosts
+#[rustfmt::skip]
+pub const CONSUMABLES: [PickUpItem; 7] = [
+    PickUpItem::HealingPotion,  PickUpItem::BlinkPotion,        PickUpItem::SpeedPotion,
+    PickUpItem::RegenerationPotion,
+    PickUpItem::TeleportScroll, PickUpItem::MagicMappingScroll, PickUpItem::FearScroll,
+];
@@ -1,7 +1,21 @@
-use crate::point::Point;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug)]
+use crate::{gear::GearSlot, point::Point};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum PlayerAction {
     MoveBy(Point),
     Select,
+    PickUp,
+    // The usize is the index of the item in the player's inventory
+    UseItem(usize),
+    DropItem(usize),
+    // ThrowItem(index, target), the item flies in a line towards the target
+    ThrowItem(usize, Point),
+    Unequip(GearSlot),
+    // Looks around for hidden things
+    Search,
+    // OpenDoor(direction), where the door is relative to the player; walking into a door opens it too
+    OpenDoor(Point),
+    CloseDoor(Point),
 }
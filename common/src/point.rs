@@ -1,8 +1,14 @@
 use std::ops::Add;
 
-use crate::{LEVEL_HEIGHT, LEVEL_WIDTH};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[rustfmt::skip]
+pub const DIRECTIONS: [Point; 8] = [
+    Point { x: 0, y: 1 },  Point { x: 1, y: 0 },  Point { x: 0, y: -1 }, Point { x: -1, y: 0 },
+    Point { x: 1, y: 1 },  Point { x: 1, y: -1 }, Point { x: -1, y: -1 }, Point { x: -1, y: 1 },
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Point {
     pub x: i32,
     pub y: i32,
@@ -11,28 +17,58 @@ impl Point {
     pub fn new(x: i32, y: i32) -> Self {
         Point { x, y }
     }
-    pub fn in_bounds(self) -> bool {
-        self.x >= 0 && self.y >= 0 && self.x < LEVEL_WIDTH as i32 && self.y < LEVEL_HEIGHT as i32
+    // Whether the point is on a level of the given size
+    pub fn in_bounds(self, width: i32, height: i32) -> bool {
+        self.x >= 0 && self.y >= 0 && self.x < width && self.y < height
     }
-    // Doesn't include diagonal neighbors
+    // Includes diagonal neighbors
     pub fn is_neighboring(self, other: Point) -> bool {
-        (self.x == other.x && self.y.abs_diff(other.y) <= 1)
-            || (self.y == other.y && self.x.abs_diff(other.x) <= 1)
+        self.distance_to(other) <= 1
+    }
+    pub fn is_diagonal_to(self, other: Point) -> bool {
+        self.x != other.x && self.y != other.y
+    }
+    // The number of moves needed to get to other, diagonal moves included
+    pub fn distance_to(self, other: Point) -> u32 {
+        self.x.abs_diff(other.x).max(self.y.abs_diff(other.y))
+    }
+    // The points on the straight line to other (Bresenham's line), without self
+    pub fn line_to(self, other: Point) -> Vec<Point> {
+        let dx = (other.x - self.x).abs();
+        let dy = -(other.y - self.y).abs();
+        let step = Point::new((other.x - self.x).signum(), (other.y - self.y).signum());
+
+        let mut points = vec![];
+        let mut error = dx + dy;
+        let mut point = self;
+        while point != other {
+            let double_error = 2 * error;
+            if double_error >= dy {
+                error += dy;
+                point.x += step.x;
+            }
+            if double_error <= dx {
+                error += dx;
+                point.y += step.y;
+            }
+            points.push(point);
+        }
+
+        points
     }
     pub fn iter_neighbors(self) -> impl Iterator<Item = Self> {
-        vec![
-            Point::new(0, 1),
-            Point::new(1, 0),
-            Point::new(0, -1),
-            Point::new(-1, 0),
-        ]
-        .into_iter()
-        .map(move |p| self + p)
+        DIRECTIONS.into_iter().map(move |p| self + p)
+    }
+    // The points at most the given distance away, self included, even those off the level
+    pub fn iter_within(self, distance: u32) -> impl Iterator<Item = Self> {
+        let distance = distance as i32;
+        (-distance..=distance)
+            .flat_map(move |dy| (-distance..=distance).map(move |dx| self + Point::new(dx, dy)))
     }
 }
-impl Into<(isize, isize)> for Point {
-    fn into(self) -> (isize, isize) {
-        (self.y as isize, self.x as isize)
+impl From<Point> for (isize, isize) {
+    fn from(point: Point) -> Self {
+        (point.y as isize, point.x as isize)
     }
 }
 impl Add for Point {
@@ -66,20 +102,24 @@ mod tests {
     }
     #[test]
     fn point_in_bounds() {
-        assert!(Point::new(0, 0).in_bounds());
-        assert!(Point::new(5, 6).in_bounds());
-        assert!(Point::new(50, 10).in_bounds());
-        assert!(Point::new(LEVEL_WIDTH - 1, LEVEL_HEIGHT - 1).in_bounds());
-
-        assert!(!Point::new(10, 50).in_bounds());
-        assert!(!Point::new(0, -5).in_bounds());
-        assert!(!Point::new(-1, 0).in_bounds());
-        assert!(!Point::new(-4, -5).in_bounds());
-        assert!(!Point::new(90, 10).in_bounds());
-        assert!(!Point::new(90, 50).in_bounds());
-        assert!(!Point::new(LEVEL_WIDTH, 0).in_bounds());
-        assert!(!Point::new(0, LEVEL_HEIGHT).in_bounds());
-        assert!(!Point::new(LEVEL_WIDTH, LEVEL_HEIGHT).in_bounds());
+        let (width, height) = (60, 24);
+        assert!(Point::new(0, 0).in_bounds(width, height));
+        assert!(Point::new(5, 6).in_bounds(width, height));
+        assert!(Point::new(50, 10).in_bounds(width, height));
+        assert!(Point::new(width - 1, height - 1).in_bounds(width, height));
+
+        assert!(!Point::new(10, 50).in_bounds(width, height));
+        assert!(!Point::new(0, -5).in_bounds(width, height));
+        assert!(!Point::new(-1, 0).in_bounds(width, height));
+        assert!(!Point::new(-4, -5).in_bounds(width, height));
+        assert!(!Point::new(90, 10).in_bounds(width, height));
+        assert!(!Point::new(90, 50).in_bounds(width, height));
+        assert!(!Point::new(width, 0).in_bounds(width, height));
+        assert!(!Point::new(0, height).in_bounds(width, height));
+        assert!(!Point::new(width, height).in_bounds(width, height));
+
+        // A smaller level has less room
+        assert!(!Point::new(50, 10).in_bounds(20, 12));
     }
     #[test]
     fn point_is_neighboring() {
@@ -89,8 +129,11 @@ mod tests {
         assert!(point.is_neighboring(Point::new(2, 2)));
         assert!(point.is_neighboring(Point::new(1, 1)));
         assert!(point.is_neighboring(Point::new(3, 1)));
+        assert!(point.is_neighboring(Point::new(1, 0)));
+        assert!(point.is_neighboring(Point::new(3, 2)));
 
         assert!(!point.is_neighboring(Point::new(2, 3)));
+        assert!(!point.is_neighboring(Point::new(4, 2)));
         assert!(!point.is_neighboring(Point::new(0, 3)));
         assert!(!point.is_neighboring(Point::new(20, 13)));
     }
@@ -98,12 +141,65 @@ mod tests {
     fn point_iter_neighbors() {
         let point = Point::new(1, 2);
 
-        assert_eq!(point.iter_neighbors().count(), 4);
+        assert_eq!(point.iter_neighbors().count(), 8);
 
         assert!(point.iter_neighbors().any(|p| p == Point::new(1, 3)));
         assert!(point.iter_neighbors().any(|p| p == Point::new(1, 1)));
         assert!(point.iter_neighbors().any(|p| p == Point::new(0, 2)));
         assert!(point.iter_neighbors().any(|p| p == Point::new(2, 2)));
+        assert!(point.iter_neighbors().any(|p| p == Point::new(0, 1)));
+        assert!(point.iter_neighbors().any(|p| p == Point::new(2, 3)));
+        assert!(point
+            .iter_neighbors()
+            .all(|p| p.is_neighboring(point) && p != point));
+    }
+    #[test]
+    fn point_iter_within() {
+        let point = Point::new(5, 5);
+        assert_eq!(point.iter_within(0).collect::<Vec<_>>(), vec![point]);
+        assert_eq!(point.iter_within(2).count(), 25);
+        assert!(point.iter_within(2).all(|p| p.distance_to(point) <= 2));
+
+        // Points off the level are left for the caller to weed out
+        assert_eq!(
+            Point::new(0, 0)
+                .iter_within(1)
+                .filter(|p| p.in_bounds(60, 24))
+                .count(),
+            4
+        );
+    }
+    #[test]
+    fn point_distance_to() {
+        let point = Point::new(2, 1);
+
+        assert_eq!(point.distance_to(point), 0);
+        assert_eq!(point.distance_to(Point::new(3, 2)), 1);
+        assert_eq!(point.distance_to(Point::new(7, -1)), 5);
+
+        assert!(point.is_diagonal_to(Point::new(3, 0)));
+        assert!(!point.is_diagonal_to(Point::new(2, 5)));
+    }
+    #[test]
+    fn point_line_to() {
+        let point = Point::new(1, 1);
+
+        assert!(point.line_to(point).is_empty());
+        assert_eq!(
+            point.line_to(Point::new(4, 1)),
+            vec![Point::new(2, 1), Point::new(3, 1), Point::new(4, 1)]
+        );
+        assert_eq!(
+            point.line_to(Point::new(-1, -1)),
+            vec![Point::new(0, 0), Point::new(-1, -1)]
+        );
+
+        let line = point.line_to(Point::new(7, 4));
+        assert_eq!(line.len(), 6);
+        assert_eq!(line.last(), Some(&Point::new(7, 4)));
+        for (p1, p2) in line.iter().zip(line.iter().skip(1)) {
+            assert!(p1.x.abs_diff(p2.x) <= 1 && p1.y.abs_diff(p2.y) <= 1);
+        }
     }
     #[test]
     fn point_into_isize_isize() {
@@ -0,0 +1,142 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dungeon::DUNGEON_CONFIG,
+    gamecontext::GameContext,
+    monsterkind::MONSTER_KINDS,
+    playeraction::PlayerAction,
+    savefile::{SaveError, SAVE_VERSION},
+    vault::VAULTS,
+};
+
+pub const REPLAY_FILE_NAME: &str = "myrustroguelike.replay";
+
+// A whole run: the seed it was started with and every action the player took.
+// Since the game is deterministic, this is enough to play it back exactly.
+// The replay shares its version with the save files,
+// as anything changing the game's logic will make older replays go differently.
+// The same goes for the monsters, the dungeon and the vaults, whose fingerprint gets recorded.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub data: u64,
+    pub seed: u64,
+    pub actions: Vec<PlayerAction>,
+}
+impl Replay {
+    pub fn new(seed: u64, actions: Vec<PlayerAction>) -> Self {
+        Replay {
+            version: SAVE_VERSION,
+            data: data_fingerprint(),
+            seed,
+            actions,
+        }
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        fs::write(path, ron::to_string(self)?)?;
+        Ok(())
+    }
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        from_str_for(&fs::read_to_string(path)?, data_fingerprint())
+    }
+}
+// The fingerprint of the game data in use
+pub fn data_fingerprint() -> u64 {
+    [
+        DUNGEON_CONFIG.fingerprint(),
+        MONSTER_KINDS.fingerprint(),
+        VAULTS.fingerprint(),
+    ]
+    .iter()
+    .fold(0, |hash, fingerprint| hash.rotate_left(21) ^ fingerprint)
+}
+fn from_str_for(source: &str, data: u64) -> Result<Replay, SaveError> {
+    let replay: Replay = ron::from_str(source)?;
+    if replay.version != SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion(replay.version));
+    }
+    if replay.data != data {
+        return Err(SaveError::DifferentData);
+    }
+
+    Ok(replay)
+}
+
+pub struct ReplayPlayer {
+    pub context: GameContext,
+    actions: Vec<PlayerAction>,
+    next_action: usize,
+}
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        ReplayPlayer {
+            context: GameContext::new_with_seed(replay.seed),
+            actions: replay.actions,
+            next_action: 0,
+        }
+    }
+    // Plays the next recorded action, returning false if there was none left.
+    pub fn step(&mut self) -> bool {
+        if let Some(&action) = self.actions.get(self.next_action) {
+            self.context.next_turn(action);
+            self.next_action += 1;
+            true
+        } else {
+            false
+        }
+    }
+    pub fn is_finished(&self) -> bool {
+        self.next_action >= self.actions.len()
+    }
+    // (actions played, total actions)
+    pub fn progress(&self) -> (usize, usize) {
+        (self.next_action, self.actions.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point::Point;
+
+    #[test]
+    fn replay_plays_back_the_game() {
+        let mut context = GameContext::new_with_seed(3);
+        for i in 0..50 {
+            context.next_turn(PlayerAction::MoveBy(Point::new(i % 3 - 1, i % 2)));
+        }
+        context.next_turn(PlayerAction::Select);
+
+        let mut replay_player = ReplayPlayer::new(context.replay());
+        assert_eq!(replay_player.progress(), (0, 51));
+        while replay_player.step() {}
+
+        assert!(replay_player.is_finished());
+        assert_eq!(
+            format!("{:?}", replay_player.context),
+            format!("{:?}", context)
+        );
+    }
+    #[test]
+    fn replay_round_trip() {
+        let replay = Replay::new(
+            11,
+            vec![PlayerAction::MoveBy(Point::new(1, 0)), PlayerAction::Select],
+        );
+        let loaded: Replay = ron::from_str(&ron::to_string(&replay).unwrap()).unwrap();
+
+        assert_eq!(format!("{:?}", loaded), format!("{:?}", replay));
+    }
+    #[test]
+    fn replay_different_data() {
+        let replay = ron::to_string(&Replay::new(11, vec![PlayerAction::Select])).unwrap();
+        assert!(from_str_for(&replay, data_fingerprint()).is_ok());
+
+        assert!(matches!(
+            from_str_for(&replay, data_fingerprint() + 1),
+            Err(SaveError::DifferentData)
+        ));
+    }
+}
@@ -1,6 +1,6 @@
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
-use crate::{point::Point, LEVEL_HEIGHT, LEVEL_WIDTH, MAX_ROOM_WIDTH, MIN_ROOM_WIDTH};
+use crate::{dungeon::Dimensions, point::Point};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Room {
@@ -14,27 +14,27 @@ impl Room {
             bottom_right: Point::new(top_left_x + width, top_left_y + height),
         }
     }
-    pub fn generate() -> Self {
-        let mut rng = thread_rng();
-
-        let width = rng.gen_range(MIN_ROOM_WIDTH, MAX_ROOM_WIDTH);
-        let height = rng.gen_range(MIN_ROOM_WIDTH, MAX_ROOM_WIDTH);
-        let top_left_x = rng.gen_range(0, LEVEL_WIDTH - width);
-        let top_left_y = rng.gen_range(0, LEVEL_HEIGHT - height);
+    pub fn generate(dimensions: Dimensions, rng: &mut impl Rng) -> Self {
+        let width = rng.gen_range(dimensions.min_room_width, dimensions.max_room_width);
+        let height = rng.gen_range(dimensions.min_room_width, dimensions.max_room_width);
+        let top_left_x = rng.gen_range(0, dimensions.width - width);
+        let top_left_y = rng.gen_range(0, dimensions.height - height);
 
         Room::new(top_left_x, top_left_y, width, height)
     }
-    pub fn generate_not_overlapping(room: Room) -> Self {
+    pub fn generate_not_overlapping(
+        room: Room,
+        dimensions: Dimensions,
+        rng: &mut impl Rng,
+    ) -> Self {
         loop {
-            let proposed = Room::generate();
+            let proposed = Room::generate(dimensions, rng);
             if !room.overlaps(proposed) {
                 break proposed;
             }
         }
     }
-    pub fn generate_inner_point(self) -> Point {
-        let mut rng = thread_rng();
-
+    pub fn generate_inner_point(self, rng: &mut impl Rng) -> Point {
         Point::new(
             rng.gen_range(self.top_left.x + 1, self.bottom_right.x),
             rng.gen_range(self.top_left.y + 1, self.bottom_right.y),
@@ -50,7 +50,10 @@ impl Room {
 
 #[cfg(test)]
 mod tests {
+    use rand::SeedableRng;
+
     use super::*;
+    use crate::{dungeon::dungeon_config, GameRng};
 
     #[test]
     fn room_new() {
@@ -76,24 +79,46 @@ mod tests {
     }
     #[test]
     fn room_generate() {
-        for _ in 0..100 {
-            let room = Room::generate();
-            assert!(room.top_left.in_bounds());
-            assert!(room.bottom_right.in_bounds());
+        let mut rng = GameRng::seed_from_u64(0);
+        for dimensions in [
+            dungeon_config().dimensions,
+            Dimensions {
+                width: 20,
+                height: 12,
+                min_room_width: 3,
+                max_room_width: 6,
+            },
+        ] {
+            let mut widths = vec![];
+            for _ in 0..100 {
+                let room = Room::generate(dimensions, &mut rng);
+                assert!(room.top_left.in_bounds(dimensions.width, dimensions.height));
+                assert!(room
+                    .bottom_right
+                    .in_bounds(dimensions.width, dimensions.height));
+                widths.push(room.bottom_right.x - room.top_left.x);
+            }
+            assert_eq!(widths.iter().min(), Some(&dimensions.min_room_width));
+            assert_eq!(widths.iter().max(), Some(&(dimensions.max_room_width - 1)));
         }
     }
     #[test]
     fn room_generate_not_overlapping() {
+        let mut rng = GameRng::seed_from_u64(0);
         let room = Room::new(30, 10, 9, 5);
         for _ in 0..100 {
-            assert!(!Room::generate_not_overlapping(room).overlaps(room));
+            assert!(
+                !Room::generate_not_overlapping(room, dungeon_config().dimensions, &mut rng)
+                    .overlaps(room)
+            );
         }
     }
     #[test]
     fn room_generate_inner_point() {
+        let mut rng = GameRng::seed_from_u64(0);
         let room = Room::new(16, 9, 8, 7);
         for _ in 0..100 {
-            let point = room.generate_inner_point();
+            let point = room.generate_inner_point(&mut rng);
             assert!(
                 point.x > room.top_left.x
                     && point.x < room.bottom_right.x
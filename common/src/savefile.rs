@@ -0,0 +1,170 @@
+use std::{error::Error, fmt::Display, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dungeon::{dungeon_config, DungeonConfig},
+    gamecontext::GameContext,
+};
+
+// Bump this whenever a change to the game state makes older saves unreadable.
+pub const SAVE_VERSION: u32 = 1;
+pub const SAVE_FILE_NAME: &str = "myrustroguelike.sav";
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Format(ron::Error),
+    UnsupportedVersion(u32),
+    // The save was made under a dungeon configuration with other levels
+    DifferentDungeon,
+    // The replay was recorded with other monsters, dungeon configuration or vaults
+    DifferentData,
+}
+impl Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "Couldn't access the save file: {}", error),
+            SaveError::Format(error) => write!(f, "The save file is corrupted: {}", error),
+            SaveError::UnsupportedVersion(version) => write!(
+                f,
+                "The save file has version {}, but only version {} is supported",
+                version, SAVE_VERSION
+            ),
+            SaveError::DifferentDungeon => write!(
+                f,
+                "The save file was made for a dungeon with a different number or size of levels"
+            ),
+            SaveError::DifferentData => write!(
+                f,
+                "The replay was recorded with different monsters, dungeon configuration or vaults"
+            ),
+        }
+    }
+}
+impl Error for SaveError {}
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+impl From<ron::Error> for SaveError {
+    fn from(error: ron::Error) -> Self {
+        SaveError::Format(error)
+    }
+}
+impl From<ron::error::SpannedError> for SaveError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        SaveError::Format(error.code)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename = "SaveFile")]
+struct SaveFileRef<'a> {
+    version: u32,
+    context: &'a GameContext,
+}
+// Only the version is read first, so that saves from other versions
+// get reported as such instead of as corrupted files.
+#[derive(Deserialize)]
+#[serde(rename = "SaveFile")]
+struct SaveFileHeader {
+    version: u32,
+}
+#[derive(Deserialize)]
+struct SaveFile {
+    context: GameContext,
+}
+
+pub fn to_string(context: &GameContext) -> Result<String, SaveError> {
+    Ok(ron::to_string(&SaveFileRef {
+        version: SAVE_VERSION,
+        context,
+    })?)
+}
+pub fn from_str(save: &str) -> Result<GameContext, SaveError> {
+    from_str_for(save, dungeon_config())
+}
+fn from_str_for(save: &str, config: &DungeonConfig) -> Result<GameContext, SaveError> {
+    let header: SaveFileHeader = ron::from_str(save)?;
+    if header.version != SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion(header.version));
+    }
+
+    let save_file: SaveFile = ron::from_str(save)?;
+    if !save_file.context.fits_dungeon(config) {
+        return Err(SaveError::DifferentDungeon);
+    }
+    Ok(save_file.context)
+}
+
+pub fn save(context: &GameContext, path: impl AsRef<Path>) -> Result<(), SaveError> {
+    fs::write(path, to_string(context)?)?;
+    Ok(())
+}
+pub fn load(path: impl AsRef<Path>) -> Result<GameContext, SaveError> {
+    from_str(&fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{playeraction::PlayerAction, point::Point};
+
+    #[test]
+    fn savefile_round_trip() {
+        let mut context = GameContext::new_with_seed(7);
+        for _ in 0..30 {
+            context.next_turn(PlayerAction::MoveBy(Point::new(1, 0)));
+        }
+        let mut loaded = from_str(&to_string(&context).unwrap()).unwrap();
+        assert_eq!(format!("{:?}", loaded), format!("{:?}", context));
+
+        // The RNG state is saved too, so the game goes on the same way
+        for _ in 0..30 {
+            context.next_turn(PlayerAction::MoveBy(Point::new(0, 1)));
+            loaded.next_turn(PlayerAction::MoveBy(Point::new(0, 1)));
+        }
+        assert_eq!(format!("{:?}", loaded), format!("{:?}", context));
+    }
+    #[test]
+    fn savefile_unsupported_version() {
+        let save = to_string(&GameContext::new_with_seed(7)).unwrap().replacen(
+            &format!("version:{}", SAVE_VERSION),
+            "version:0",
+            1,
+        );
+
+        match from_str(&save) {
+            Err(SaveError::UnsupportedVersion(0)) => {}
+            other => panic!("{:?}", other.map(|_| ())),
+        }
+    }
+    #[test]
+    fn savefile_different_dungeon() {
+        let save = to_string(&GameContext::new_with_seed(7)).unwrap();
+        assert!(from_str_for(&save, dungeon_config()).is_ok());
+
+        let mut config = dungeon_config().clone();
+        config.level_count -= 1;
+        assert!(matches!(
+            from_str_for(&save, &config),
+            Err(SaveError::DifferentDungeon)
+        ));
+
+        let mut config = dungeon_config().clone();
+        config.dimensions.width += 1;
+        assert!(matches!(
+            from_str_for(&save, &config),
+            Err(SaveError::DifferentDungeon)
+        ));
+    }
+    #[test]
+    fn savefile_corrupted() {
+        assert!(matches!(
+            from_str("SaveFile(version:1, context:"),
+            Err(SaveError::Format(_))
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+use std::{cmp::max, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::MAX_POISON_TURNS;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatusKind {
+    // Loses 1 HP every turn
+    Poison,
+    // Gains 1 HP every turn
+    Regeneration,
+    // Can't act
+    Stun,
+    // Twice as fast
+    Haste,
+    // Half as fast
+    Slow,
+    // Stumbles in a random direction half of the time
+    Confusion,
+    // Sees only what's right next to them
+    Blindness,
+}
+impl StatusKind {
+    // The status this one cancels out
+    fn opposite(self) -> Option<StatusKind> {
+        match self {
+            StatusKind::Haste => Some(StatusKind::Slow),
+            StatusKind::Slow => Some(StatusKind::Haste),
+            _ => None,
+        }
+    }
+}
+impl Display for StatusKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                StatusKind::Poison => "Poisoned",
+                StatusKind::Regeneration => "Regenerating",
+                StatusKind::Stun => "Stunned",
+                StatusKind::Haste => "Hasted",
+                StatusKind::Slow => "Slowed",
+                StatusKind::Confusion => "Confused",
+                StatusKind::Blindness => "Blind",
+            }
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Status {
+    pub kind: StatusKind,
+    // How many more turns it lasts
+    pub turns: u32,
+}
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.kind, self.turns)
+    }
+}
+
+// The temporary states a being is in
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Statuses(Vec<Status>);
+impl Statuses {
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.0.iter().any(|status| status.kind == kind)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Status> {
+        self.0.iter()
+    }
+    // Poison piles up, up to MAX_POISON_TURNS, while the other statuses last
+    // for the longest of the durations they got. Haste and slowness cancel each other out.
+    pub fn add(&mut self, kind: StatusKind, turns: u32) {
+        if let Some(opposite) = kind.opposite() {
+            if self.has(opposite) {
+                self.0.retain(|status| status.kind != opposite);
+                return;
+            }
+        }
+
+        match self.0.iter_mut().find(|status| status.kind == kind) {
+            Some(status) if kind == StatusKind::Poison => {
+                status.turns = (status.turns + turns).min(MAX_POISON_TURNS);
+            }
+            Some(status) => status.turns = max(status.turns, turns),
+            None => self.0.push(Status { kind, turns }),
+        }
+    }
+    // Counts down a turn of every status, returning the ones that wore off
+    pub fn tick(&mut self) -> Vec<StatusKind> {
+        for status in self.0.iter_mut() {
+            status.turns = status.turns.saturating_sub(1);
+        }
+        let worn_off = self
+            .0
+            .iter()
+            .filter(|status| status.turns == 0)
+            .map(|status| status.kind)
+            .collect();
+        self.0.retain(|status| status.turns > 0);
+
+        worn_off
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statuses_stacking() {
+        let mut statuses = Statuses::default();
+        statuses.add(StatusKind::Poison, 5);
+        statuses.add(StatusKind::Poison, 5);
+        statuses.add(StatusKind::Poison, MAX_POISON_TURNS);
+        statuses.add(StatusKind::Confusion, 5);
+        statuses.add(StatusKind::Confusion, 3);
+        assert_eq!(
+            statuses.iter().copied().collect::<Vec<_>>(),
+            vec![
+                Status {
+                    kind: StatusKind::Poison,
+                    turns: MAX_POISON_TURNS
+                },
+                Status {
+                    kind: StatusKind::Confusion,
+                    turns: 5
+                },
+            ]
+        );
+
+        statuses.add(StatusKind::Haste, 10);
+        statuses.add(StatusKind::Slow, 3);
+        assert!(!statuses.has(StatusKind::Haste));
+        assert!(!statuses.has(StatusKind::Slow));
+    }
+    #[test]
+    fn statuses_tick() {
+        let mut statuses = Statuses::default();
+        statuses.add(StatusKind::Stun, 1);
+        statuses.add(StatusKind::Blindness, 2);
+
+        assert_eq!(statuses.tick(), vec![StatusKind::Stun]);
+        assert!(statuses.has(StatusKind::Blindness));
+        assert_eq!(statuses.tick(), vec![StatusKind::Blindness]);
+        assert_eq!(statuses.iter().count(), 0);
+    }
+    #[test]
+    fn status_display() {
+        let status = Status {
+            kind: StatusKind::Blindness,
+            turns: 3,
+        };
+        assert_eq!(status.to_string(), "Blind (3)");
+    }
+}
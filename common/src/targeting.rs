@@ -0,0 +1,76 @@
+use crate::{gamecontext::GameContext, point::Point, THROW_RANGE};
+
+// The state of a targeting cursor, used by the frontends to pick what to throw things at.
+// It starts on the closest visible enemy, and can cycle through the others or move freely.
+#[derive(Clone, Copy, Debug)]
+pub struct Targeting {
+    pub target: Point,
+}
+impl Targeting {
+    pub fn new(context: &GameContext) -> Self {
+        Targeting {
+            target: context
+                .visible_enemies()
+                .first()
+                .map_or(context.player.position, |enemy| enemy.position),
+        }
+    }
+    pub fn next_enemy(&mut self, context: &GameContext) {
+        let enemies = context.visible_enemies();
+        if enemies.is_empty() {
+            return;
+        }
+
+        let next_index = enemies
+            .iter()
+            .position(|enemy| enemy.position == self.target)
+            .map_or(0, |i| (i + 1) % enemies.len());
+        self.target = enemies[next_index].position;
+    }
+    pub fn move_by(&mut self, context: &GameContext, move_by: Point) {
+        let new_target = self.target + move_by;
+        if context.level.tiles.in_bounds(new_target) {
+            self.target = new_target;
+        }
+    }
+    // The tiles the thrown item would fly over
+    pub fn path(self, context: &GameContext) -> Vec<Point> {
+        context
+            .level
+            .projectile_path(context.player.position, self.target, THROW_RANGE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        being::Being, beingkind::BeingKind, gamecontext::tests::arena, playeraction::PlayerAction,
+    };
+
+    #[test]
+    fn targeting_cycles_through_visible_enemies() {
+        let mut context = arena();
+        let player_position = context.player.position;
+        for offset in [Point::new(2, 0), Point::new(-4, 1), Point::new(0, 8)] {
+            let mut enemy = Being::new_player();
+            enemy.kind = BeingKind::named("Animated Statue").unwrap();
+            enemy.position = player_position + offset;
+            context.level.enemies.push(enemy);
+        }
+        context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
+
+        let mut targeting = Targeting::new(&context);
+        let first_target = targeting.target;
+        assert_eq!(first_target, context.visible_enemies()[0].position);
+
+        targeting.next_enemy(&context);
+        assert_ne!(targeting.target, first_target);
+        targeting.next_enemy(&context);
+        targeting.next_enemy(&context);
+        assert_eq!(targeting.target, first_target);
+
+        targeting.move_by(&context, Point::new(1, 0));
+        assert_eq!(targeting.target, first_target + Point::new(1, 0));
+    }
+}
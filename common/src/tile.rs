@@ -1,35 +1,74 @@
-use crate::pickupitem::PickUpItem;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+use crate::{pickupitem::PickUpItem, trap::Trap};
+
+// Closed doors can be opened by the player and by the monsters that know how to,
+// while locked ones need a key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DoorState {
+    Open,
+    Closed,
+    Locked,
+    // Looks like a wall until the player finds it, turning into a closed door
+    Secret,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Tile {
     Wall,
     Room,
     // Stairs(level_progression) = stairs that lead to level current_level + level_progression
     Stairs(i32),
-    Door,
+    Door(DoorState),
     Item(PickUpItem),
+    Trap(Trap),
 }
 impl Tile {
     pub fn is_walkable(self) -> bool {
         match self {
-            Tile::Wall => false,
-            Tile::Room | Tile::Stairs(_) | Tile::Door | Tile::Item(_) => true,
+            Tile::Wall | Tile::Door(DoorState::Closed | DoorState::Locked | DoorState::Secret) => {
+                false
+            }
+            Tile::Room
+            | Tile::Stairs(_)
+            | Tile::Door(DoorState::Open)
+            | Tile::Item(_)
+            | Tile::Trap(_) => true,
         }
     }
     pub fn is_blocking(self) -> bool {
         match self {
-            Tile::Wall | Tile::Door => true,
-            Tile::Room | Tile::Stairs(_) | Tile::Item(_) => false,
+            Tile::Wall | Tile::Door(DoorState::Closed | DoorState::Locked | DoorState::Secret) => {
+                true
+            }
+            Tile::Room
+            | Tile::Stairs(_)
+            | Tile::Door(DoorState::Open)
+            | Tile::Item(_)
+            | Tile::Trap(_) => false,
         }
     }
-    pub fn get_name(self) -> String {
+    pub fn is_door(self) -> bool {
+        matches!(self, Tile::Door(_))
+    }
+    // What the tile looks like to the player: hidden traps look like the floor,
+    // and secret doors like the walls around them
+    pub fn as_seen(self) -> Tile {
         match self {
+            Tile::Trap(trap) if !trap.found => Tile::Room,
+            Tile::Door(DoorState::Secret) => Tile::Wall,
+            tile => tile,
+        }
+    }
+    pub fn get_name(self) -> String {
+        match self.as_seen() {
+            Tile::Trap(trap) => trap.kind.to_string(),
             Tile::Room => "Room".to_string(),
-            Tile::Item(PickUpItem::HealthBoost) => "Health boost".to_string(),
-            Tile::Item(PickUpItem::AttackBoost) => "Attack boost".to_string(),
-            Tile::Item(PickUpItem::ShieldBoost) => "Shield boost".to_string(),
-            Tile::Wall => "Wall".to_string(),
-            Tile::Door => "Door".to_string(),
+            Tile::Item(item) => item.to_string(),
+            Tile::Wall | Tile::Door(DoorState::Secret) => "Wall".to_string(),
+            Tile::Door(DoorState::Open) => "Open Door".to_string(),
+            Tile::Door(DoorState::Closed) => "Closed Door".to_string(),
+            Tile::Door(DoorState::Locked) => "Locked Door".to_string(),
             Tile::Stairs(_) => "Stairs".to_string(),
         }
     }
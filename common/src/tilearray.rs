@@ -1,28 +1,121 @@
 use std::{
     cmp::{max, min},
+    collections::VecDeque,
     ops::{Index, IndexMut},
 };
 
-use rand::random;
+use pathfinding::prelude::dijkstra;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::{point::Point, room::Room, tile::Tile, LEVEL_HEIGHT, LEVEL_WIDTH};
+use crate::{
+    point::{Point, DIRECTIONS},
+    room::Room,
+    tile::{DoorState, Tile},
+    MAX_SIDE_CORRIDOR_LENGTH,
+};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TileArray<T> {
+    width: i32,
+    height: i32,
     array: Vec<T>,
 }
 impl<T: Copy> TileArray<T> {
-    pub fn new(default: T) -> Self {
+    pub fn new(width: i32, height: i32, default: T) -> Self {
         TileArray {
-            array: vec![default; (LEVEL_HEIGHT * LEVEL_WIDTH) as usize],
+            width,
+            height,
+            array: vec![default; (height * width) as usize],
         }
     }
 }
+impl<T> TileArray<T> {
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+    pub fn in_bounds(&self, point: Point) -> bool {
+        point.in_bounds(self.width, self.height)
+    }
+    // Every point of the array, row by row
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let (width, height) = (self.width, self.height);
+        (0..height).flat_map(move |y| (0..width).map(move |x| Point::new(x, y)))
+    }
+}
 impl TileArray<Tile> {
-    pub fn generate_floor_point(&self) -> Point {
-        let whole_level = Room::new(0, 0, LEVEL_WIDTH - 1, LEVEL_HEIGHT - 1);
+    // Whether nothing blocks a projectile flying from a point to another
+    pub fn has_line_of_fire(&self, from: Point, to: Point) -> bool {
+        let line = from.line_to(to);
+        line.iter()
+            .take(line.len().saturating_sub(1))
+            .all(|&point| self.in_bounds(point) && !self[point].is_blocking())
+    }
+    // The points the player can get to from the start, opening the doors that aren't locked
+    pub fn reachable_from(&self, start: Point) -> Vec<Point> {
+        self.flood_fill(start, |tile| {
+            tile.is_walkable() || tile == Tile::Door(DoorState::Closed)
+        })
+    }
+    // The points that can be got to from the start going over the tiles it's possible to enter,
+    // in the order of their distance from it. Doors can't be entered or left diagonally.
+    pub fn flood_fill(&self, start: Point, can_enter: impl Fn(Tile) -> bool) -> Vec<Point> {
+        let mut reached = TileArray::new(self.width, self.height, false);
+        reached[start] = true;
+        let mut queue = VecDeque::from([start]);
+        let mut points = vec![];
+        while let Some(point) = queue.pop_front() {
+            points.push(point);
+            for neighbor in point.iter_neighbors() {
+                if self.in_bounds(neighbor)
+                    && !reached[neighbor]
+                    && can_enter(self[neighbor])
+                    && self.keeps_doors_straight(point, neighbor)
+                {
+                    reached[neighbor] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        points
+    }
+    // The secret doors that have to be found to get from the start to the end,
+    // going through as few of them as possible and opening the doors that aren't locked.
+    // Empty if they aren't needed, or if there's no way there even through them.
+    pub fn secret_doors_between(&self, start: Point, end: Point) -> Vec<Point> {
+        let is_secret = |point: Point| self[point] == Tile::Door(DoorState::Secret);
+        dijkstra(
+            &start,
+            |&point| {
+                point
+                    .iter_neighbors()
+                    .filter(move |&neighbor| {
+                        self.in_bounds(neighbor)
+                            && (self[neighbor].is_walkable()
+                                || self[neighbor] == Tile::Door(DoorState::Closed)
+                                || is_secret(neighbor))
+                            && self.keeps_doors_straight(point, neighbor)
+                    })
+                    .map(move |neighbor| (neighbor, is_secret(neighbor) as u32))
+            },
+            |&point| point == end,
+        )
+        .map_or(vec![], |(path, _)| {
+            path.into_iter().filter(|&point| is_secret(point)).collect()
+        })
+    }
+    // Doors can't be entered or left diagonally
+    fn keeps_doors_straight(&self, from: Point, to: Point) -> bool {
+        !from.is_diagonal_to(to) || (!self[from].is_door() && !self[to].is_door())
+    }
+    pub fn generate_floor_point(&self, rng: &mut impl Rng) -> Point {
+        let whole_level = Room::new(0, 0, self.width - 1, self.height - 1);
         loop {
-            let proposed_position = whole_level.generate_inner_point();
+            let proposed_position = whole_level.generate_inner_point(rng);
             if self[proposed_position] == Tile::Room {
                 break proposed_position;
             }
@@ -70,11 +163,11 @@ impl TileArray<Tile> {
             self[(y, x)] = Tile::Room;
         }
     }
-    pub fn carve_corridor_between(&mut self, room1: Room, room2: Room) {
-        let room1_point = room1.generate_inner_point();
-        let room2_point = room2.generate_inner_point();
+    pub fn carve_corridor_between(&mut self, room1: Room, room2: Room, rng: &mut impl Rng) {
+        let room1_point = room1.generate_inner_point(rng);
+        let room2_point = room2.generate_inner_point(rng);
 
-        if random() {
+        if rng.gen() {
             self.carve_h_corridor(room1_point.y, room1_point.x, room2_point.x);
             self.carve_v_corridor(room2_point.x, room1_point.y, room2_point.y);
         } else {
@@ -82,6 +175,84 @@ impl TileArray<Tile> {
             self.carve_h_corridor(room2_point.y, room1_point.x, room2_point.x);
         }
     }
+    // Carves a small room somewhere in the rock, joined to the rest of the level
+    // by a straight corridor walled off behind a secret door.
+    // It's a bit wider and taller than the smallest rooms can get.
+    // Returns the room, unless no spot for it was found.
+    pub fn carve_hidden_room(&mut self, min_room_width: i32, rng: &mut impl Rng) -> Option<Room> {
+        for _ in 0..20 {
+            let width = rng.gen_range(min_room_width, min_room_width + 3);
+            let height = rng.gen_range(min_room_width, min_room_width + 3);
+            if width + 3 > self.width || height + 3 > self.height {
+                continue;
+            }
+            let room = Room::new(
+                rng.gen_range(1, self.width - width - 1),
+                rng.gen_range(1, self.height - height - 1),
+                width,
+                height,
+            );
+            // Nothing can be carved right next to it, or it wouldn't be hidden
+            let is_solid = ((room.top_left.y - 1)..=(room.bottom_right.y + 1)).all(|y| {
+                ((room.top_left.x - 1)..=(room.bottom_right.x + 1))
+                    .all(|x| self[(y, x)] == Tile::Wall)
+            });
+            if !is_solid {
+                continue;
+            }
+
+            // The corridor goes straight out of the room
+            let direction = DIRECTIONS[rng.gen_range(0, 4)];
+            let mut point = room.generate_inner_point(rng);
+            while point.x > room.top_left.x
+                && point.x < room.bottom_right.x
+                && point.y > room.top_left.y
+                && point.y < room.bottom_right.y
+            {
+                point = point + direction;
+            }
+            let corridor = match self.dig_corridor(point, direction) {
+                Some(corridor) => corridor,
+                None => continue,
+            };
+
+            self.carve_room(room);
+            for &point in corridor.iter() {
+                self[point] = Tile::Room;
+            }
+            self[corridor[corridor.len() - 1]] = Tile::Door(DoorState::Secret);
+
+            return Some(room);
+        }
+
+        None
+    }
+    // The points of a straight corridor from the start that would run into the floor,
+    // going through the rock with rock on both sides of it, if there's such a corridor
+    pub fn dig_corridor(&self, start: Point, direction: Point) -> Option<Vec<Point>> {
+        let sides = [
+            Point::new(direction.y, direction.x),
+            Point::new(-direction.y, -direction.x),
+        ];
+        let mut point = start;
+        let mut corridor = vec![];
+        while self.in_bounds(point)
+            && self[point] == Tile::Wall
+            && sides
+                .iter()
+                .all(|&side| self.in_bounds(point + side) && self[point + side] == Tile::Wall)
+            && corridor.len() < MAX_SIDE_CORRIDOR_LENGTH
+        {
+            corridor.push(point);
+            point = point + direction;
+        }
+
+        if !corridor.is_empty() && self.in_bounds(point) && self[point] == Tile::Room {
+            Some(corridor)
+        } else {
+            None
+        }
+    }
     pub fn is_pillar_worthy(&self, pillar: Point) -> bool {
         (0..9)
             .map(|i| Point::new(i % 3 - 1, i / 3 - 1))
@@ -97,12 +268,12 @@ impl<T> Index<(i32, i32)> for TileArray<T> {
     type Output = T;
 
     fn index(&self, index: (i32, i32)) -> &Self::Output {
-        &self.array[(index.0 * LEVEL_WIDTH + index.1) as usize]
+        &self.array[(index.0 * self.width + index.1) as usize]
     }
 }
 impl<T> IndexMut<(i32, i32)> for TileArray<T> {
     fn index_mut(&mut self, index: (i32, i32)) -> &mut Self::Output {
-        &mut self.array[(index.0 * LEVEL_WIDTH + index.1) as usize]
+        &mut self.array[(index.0 * self.width + index.1) as usize]
     }
 }
 impl<T> Index<Point> for TileArray<T> {
@@ -120,33 +291,47 @@ impl<T> IndexMut<Point> for TileArray<T> {
 
 #[cfg(test)]
 mod tests {
+    use rand::SeedableRng;
+
     use super::*;
+    use crate::{dungeon::dungeon_config, GameRng};
 
     #[test]
     fn tilearray() {
-        let mut tile_array = TileArray::<Tile>::new(Tile::Wall);
+        let mut tile_array = TileArray::new(60, 24, Tile::Wall);
         tile_array[(2, 1)] = Tile::Room;
 
         assert_eq!(tile_array[Point::new(1, 2)], Tile::Room);
         assert_eq!(tile_array[Point::new(2, 1)], Tile::Wall);
     }
     #[test]
+    fn tilearray_size() {
+        let tile_array = TileArray::new(5, 3, 0);
+
+        assert_eq!((tile_array.width(), tile_array.height()), (5, 3));
+        assert!(tile_array.in_bounds(Point::new(4, 2)));
+        assert!(!tile_array.in_bounds(Point::new(5, 0)));
+        assert!(!tile_array.in_bounds(Point::new(0, 3)));
+        assert_eq!(tile_array.points().count(), 15);
+        assert_eq!(tile_array.points().last(), Some(Point::new(4, 2)));
+    }
+    #[test]
     #[should_panic]
     fn tilearray_panic_1() {
-        let tile_array = TileArray::<Tile>::new(Tile::Wall);
+        let tile_array = TileArray::new(60, 24, Tile::Wall);
 
-        tile_array[(0, -1)];
+        let _ = tile_array[(0, -1)];
     }
     #[test]
     #[should_panic]
     fn tilearray_panic_2() {
-        let tile_array = TileArray::<Tile>::new(Tile::Wall);
+        let tile_array = TileArray::new(60, 24, Tile::Wall);
 
-        tile_array[(LEVEL_HEIGHT, 0)];
+        let _ = tile_array[(24, 0)];
     }
     #[test]
     fn tilearray_carve_room() {
-        let mut tile_array = TileArray::<Tile>::new(Tile::Wall);
+        let mut tile_array = TileArray::new(60, 24, Tile::Wall);
         let room = Room::new(8, 9, 8, 6);
         tile_array.carve_room(room);
 
@@ -157,7 +342,7 @@ mod tests {
     }
     #[test]
     fn tilearray_carve_h_corridor() {
-        let mut tile_array = TileArray::<Tile>::new(Tile::Wall);
+        let mut tile_array = TileArray::new(60, 24, Tile::Wall);
         tile_array.carve_h_corridor(7, 2, 20);
 
         assert_eq!(tile_array[(7, 2)], Tile::Room);
@@ -170,7 +355,7 @@ mod tests {
     }
     #[test]
     fn tilearray_carve_v_corridor() {
-        let mut tile_array = TileArray::<Tile>::new(Tile::Wall);
+        let mut tile_array = TileArray::new(60, 24, Tile::Wall);
         tile_array.carve_v_corridor(7, 2, 20);
 
         assert_eq!(tile_array[(2, 7)], Tile::Room);
@@ -183,7 +368,7 @@ mod tests {
     }
     #[test]
     fn tilearray_is_pillar_worthy() {
-        let mut tile_array = TileArray::<Tile>::new(Tile::Wall);
+        let mut tile_array = TileArray::new(60, 24, Tile::Wall);
         let room = Room::new(8, 9, 8, 6);
         tile_array.carve_room(room);
 
@@ -194,7 +379,7 @@ mod tests {
     }
     #[test]
     fn tilearray_is_door_worthy() {
-        let mut tile_array = TileArray::<Tile>::new(Tile::Wall);
+        let mut tile_array = TileArray::new(60, 24, Tile::Wall);
         tile_array[(2, 1)] = Tile::Room;
         tile_array[(2, 3)] = Tile::Room;
         tile_array[(3, 3)] = Tile::Room;
@@ -209,12 +394,95 @@ mod tests {
         assert!(!tile_array.is_door_worthy(Point::new(4, 4)));
     }
     #[test]
+    fn tilearray_has_line_of_fire() {
+        let mut tile_array = TileArray::new(60, 24, Tile::Wall);
+        tile_array.carve_room(Room::new(0, 0, 10, 10));
+        let (from, to) = (Point::new(2, 2), Point::new(8, 2));
+        assert!(tile_array.has_line_of_fire(from, to));
+
+        tile_array[Point::new(5, 2)] = Tile::Door(DoorState::Open);
+        assert!(tile_array.has_line_of_fire(from, to));
+        tile_array[Point::new(5, 2)] = Tile::Door(DoorState::Closed);
+        assert!(!tile_array.has_line_of_fire(from, to));
+        assert!(tile_array.has_line_of_fire(from, Point::new(2, 8)));
+    }
+    #[test]
+    fn tilearray_reachable_from() {
+        let mut tile_array = TileArray::new(60, 24, Tile::Wall);
+        tile_array.carve_room(Room::new(0, 0, 4, 4));
+        tile_array.carve_room(Room::new(4, 0, 8, 4));
+        let (start, door) = (Point::new(2, 2), Point::new(4, 2));
+
+        assert!(!tile_array.reachable_from(start).contains(&Point::new(6, 2)));
+        tile_array[door] = Tile::Door(DoorState::Closed);
+        assert!(tile_array.reachable_from(start).contains(&Point::new(6, 2)));
+        tile_array[door] = Tile::Door(DoorState::Locked);
+        assert!(!tile_array.reachable_from(start).contains(&Point::new(6, 2)));
+        assert_eq!(tile_array.reachable_from(start).len(), 9);
+    }
+    #[test]
+    fn tilearray_secret_doors_between() {
+        let mut tile_array = TileArray::new(60, 24, Tile::Wall);
+        tile_array.carve_room(Room::new(0, 0, 4, 4));
+        tile_array.carve_room(Room::new(4, 0, 8, 4));
+        tile_array.carve_room(Room::new(0, 4, 4, 4));
+        let (start, end) = (Point::new(6, 2), Point::new(2, 6));
+        let (needed, other, way) = (Point::new(4, 2), Point::new(2, 4), Point::new(6, 4));
+        for door in [needed, other] {
+            tile_array[door] = Tile::Door(DoorState::Secret);
+        }
+
+        // Only the doors on the way there are needed
+        assert_eq!(
+            tile_array.secret_doors_between(start, Point::new(2, 2)),
+            vec![needed]
+        );
+        assert_eq!(
+            tile_array.secret_doors_between(start, end),
+            vec![needed, other]
+        );
+
+        tile_array.carve_v_corridor(way.x, way.y, 6);
+        tile_array.carve_h_corridor(6, 2, way.x);
+        assert!(tile_array.secret_doors_between(start, end).is_empty());
+    }
+    #[test]
+    fn tilearray_carve_hidden_room() {
+        let mut tile_array = TileArray::new(60, 24, Tile::Wall);
+        tile_array.carve_room(Room::new(20, 5, 20, 14));
+        let outside = Point::new(30, 10);
+        let mut rng = GameRng::seed_from_u64(0);
+
+        let mut carved = 0;
+        for _ in 0..20 {
+            let mut tiles = tile_array.clone();
+            if let Some(room) = tiles.carve_hidden_room(4, &mut rng) {
+                carved += 1;
+                let inside = Point::new(room.top_left.x + 1, room.top_left.y + 1);
+                assert!(!tiles.reachable_from(inside).contains(&outside));
+
+                let secret_doors: Vec<Point> = tiles
+                    .points()
+                    .filter(|&p| tiles[p] == Tile::Door(DoorState::Secret))
+                    .collect();
+                assert_eq!(secret_doors.len(), 1);
+                tiles[secret_doors[0]] = Tile::Door(DoorState::Closed);
+                assert!(tiles.reachable_from(inside).contains(&outside));
+            }
+        }
+        assert!(carved > 0);
+    }
+    #[test]
     fn tilearray_generate_floor_point() {
+        let mut rng = GameRng::seed_from_u64(0);
         for _ in 0..100 {
-            let mut tile_array = TileArray::<Tile>::new(Tile::Wall);
-            tile_array.carve_room(Room::generate());
+            let mut tile_array = TileArray::new(60, 24, Tile::Wall);
+            tile_array.carve_room(Room::generate(dungeon_config().dimensions, &mut rng));
 
-            assert_eq!(tile_array[tile_array.generate_floor_point()], Tile::Room);
+            assert_eq!(
+                tile_array[tile_array.generate_floor_point(&mut rng)],
+                Tile::Room
+            );
         }
     }
 }
@@ -0,0 +1,55 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dungeon::dungeon_config;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrapKind {
+    // Shoots a dart at whoever steps on it
+    Dart,
+    // Teleports them somewhere else on the level
+    Teleport,
+    // Lets every monster on the level know where they are
+    Alarm,
+    // Drops them to the next level
+    Trapdoor,
+}
+#[rustfmt::skip]
+pub const TRAP_KINDS: [TrapKind; 4] = [
+    TrapKind::Dart, TrapKind::Teleport, TrapKind::Alarm, TrapKind::Trapdoor,
+];
+impl TrapKind {
+    // Trapdoors can't lead any deeper than the last level
+    pub fn allowed_on(self, level: i32) -> bool {
+        self != TrapKind::Trapdoor || level < dungeon_config().level_count
+    }
+}
+impl Display for TrapKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                TrapKind::Dart => "Dart Trap",
+                TrapKind::Teleport => "Teleport Trap",
+                TrapKind::Alarm => "Alarm Trap",
+                TrapKind::Trapdoor => "Trapdoor",
+            }
+        )
+    }
+}
+
+// Traps look like the floor around them until the player finds them.
+// The monsters only keep off the ones the player found, as going around the hidden ones
+// would give them away; they walk over those without setting them off.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trap {
+    pub kind: TrapKind,
+    pub found: bool,
+}
+impl Trap {
+    pub fn hidden(kind: TrapKind) -> Self {
+        Trap { kind, found: false }
+    }
+}
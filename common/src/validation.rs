@@ -0,0 +1,172 @@
+use std::fmt::Display;
+
+use crate::{
+    level::Level,
+    pickupitem::PickUpItem,
+    point::Point,
+    tile::{DoorState, Tile},
+    tilearray::TileArray,
+};
+
+// Something that makes a level unfit to be played
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LevelProblem {
+    UnreachableStairs(Point),
+    UnreachableItem(Point),
+    UnreachableEnemy(Point),
+    // There are fewer keys the player can get to than locked doors
+    MissingKeys,
+}
+impl LevelProblem {
+    // Whether the problem can be fixed by clearing what's out of reach,
+    // instead of throwing the whole level away
+    pub fn is_repairable(self) -> bool {
+        matches!(
+            self,
+            LevelProblem::UnreachableItem(_) | LevelProblem::UnreachableEnemy(_)
+        )
+    }
+}
+impl Display for LevelProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LevelProblem::UnreachableStairs(point) => {
+                write!(f, "The stairs at {:?} can't be reached", point)
+            }
+            LevelProblem::UnreachableItem(point) => {
+                write!(f, "The item at {:?} can't be reached", point)
+            }
+            LevelProblem::UnreachableEnemy(point) => {
+                write!(f, "The enemy at {:?} can't be reached", point)
+            }
+            LevelProblem::MissingKeys => write!(f, "Some locked doors have no key to them"),
+        }
+    }
+}
+
+// Checks that everything on the level can be got to from the up stairs.
+// Every door can be got through: the secret ones can be found, and the locked ones unlocked,
+// as long as there are enough keys to be found without going through any locked door.
+pub fn validate(level: &Level) -> Result<(), Vec<LevelProblem>> {
+    let mut reached = TileArray::new(level.tiles.width(), level.tiles.height(), false);
+    for point in level
+        .tiles
+        .flood_fill(level.up_stairs, |tile| tile.is_walkable() || tile.is_door())
+    {
+        reached[point] = true;
+    }
+    let is_reached = |point: Point| reached[point];
+
+    let mut problems = vec![];
+    if !is_reached(level.down_stairs) {
+        problems.push(LevelProblem::UnreachableStairs(level.down_stairs));
+    }
+    let mut locked_doors = 0;
+    for point in level.tiles.points() {
+        match level.tiles[point] {
+            Tile::Item(_) if !is_reached(point) => {
+                problems.push(LevelProblem::UnreachableItem(point));
+            }
+            Tile::Door(DoorState::Locked) => locked_doors += 1,
+            _ => {}
+        }
+    }
+    for enemy in level.enemies.iter() {
+        if !is_reached(enemy.position) {
+            problems.push(LevelProblem::UnreachableEnemy(enemy.position));
+        }
+    }
+
+    let keys = level
+        .tiles
+        .flood_fill(level.up_stairs, |tile| {
+            tile.is_walkable() || (tile.is_door() && tile != Tile::Door(DoorState::Locked))
+        })
+        .into_iter()
+        .filter(|&point| level.tiles[point] == Tile::Item(PickUpItem::Key))
+        .count();
+    if keys < locked_doors {
+        problems.push(LevelProblem::MissingKeys);
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::{dungeon::dungeon_config, gamecontext::tests::arena, room::Room, GameRng};
+
+    // A level with a walled off room in its middle
+    fn walled_level() -> Level {
+        let mut level = arena().level;
+        level.up_stairs = Point::new(2, 2);
+        level.down_stairs = Point::new(level.tiles.width() - 3, 2);
+        level.tiles[level.up_stairs] = Tile::Stairs(-1);
+        level.tiles[level.down_stairs] = Tile::Stairs(1);
+
+        let walls = Room::new(20, 5, 10, 10);
+        for y in walls.top_left.y..=walls.bottom_right.y {
+            for x in walls.top_left.x..=walls.bottom_right.x {
+                level.tiles[(y, x)] = Tile::Wall;
+            }
+        }
+        let mut room = TileArray::new(level.tiles.width(), level.tiles.height(), Tile::Wall);
+        room.carve_room(walls);
+        for point in room.points() {
+            if room[point] == Tile::Room {
+                level.tiles[point] = Tile::Room;
+            }
+        }
+
+        level
+    }
+
+    #[test]
+    fn validation_unreachable() {
+        let mut level = walled_level();
+        assert_eq!(validate(&level), Ok(()));
+
+        let item = Point::new(25, 10);
+        level.tiles[item] = Tile::Item(PickUpItem::HealingPotion);
+        assert_eq!(
+            validate(&level),
+            Err(vec![LevelProblem::UnreachableItem(item)])
+        );
+
+        // Any door gets the player in
+        level.tiles[(10, 20)] = Tile::Door(DoorState::Secret);
+        assert_eq!(validate(&level), Ok(()));
+
+        level.tiles[(10, 20)] = Tile::Wall;
+        level.down_stairs = Point::new(22, 7);
+        assert!(validate(&level)
+            .unwrap_err()
+            .contains(&LevelProblem::UnreachableStairs(level.down_stairs)));
+    }
+    #[test]
+    fn validation_keys() {
+        let mut level = walled_level();
+        level.tiles[(10, 20)] = Tile::Door(DoorState::Locked);
+        assert_eq!(validate(&level), Err(vec![LevelProblem::MissingKeys]));
+
+        // The key behind the door it unlocks is no good
+        level.tiles[(10, 25)] = Tile::Item(PickUpItem::Key);
+        assert_eq!(validate(&level), Err(vec![LevelProblem::MissingKeys]));
+        level.tiles[(3, 3)] = Tile::Item(PickUpItem::Key);
+        assert_eq!(validate(&level), Ok(()));
+    }
+    #[test]
+    fn validation_generated_levels() {
+        let mut rng = GameRng::seed_from_u64(0);
+        for depth in 1..=dungeon_config().level_count {
+            assert_eq!(validate(&Level::generate(depth, &mut rng)), Ok(()));
+        }
+    }
+}
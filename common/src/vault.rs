@@ -0,0 +1,384 @@
+use std::{fs, path::Path};
+
+use rand::Rng;
+use serde::Serialize;
+
+use crate::{
+    being::Being,
+    beingkind::BeingKind,
+    datafile::{DataError, DataFile},
+    gear::{Gear, GearSlot},
+    monsterkind::monster_kinds,
+    pickupitem::PickUpItem,
+    point::Point,
+    tile::{DoorState, Tile},
+    tilearray::TileArray,
+    trap::{Trap, TrapKind, TRAP_KINDS},
+};
+
+// The vault templates the game ships with, by name
+const DEFAULT_VAULTS: [(&str, &str); 3] = [
+    ("treasury", include_str!("../data/vaults/treasury.txt")),
+    ("lair", include_str!("../data/vaults/lair.txt")),
+    ("shrine", include_str!("../data/vaults/shrine.txt")),
+];
+// If a directory with this name is found next to the game, the .txt templates in it
+// replace the default ones with the same name, and the others get added to them
+pub const VAULTS_DIR_NAME: &str = "vaults";
+
+#[rustfmt::skip]
+const POTIONS: [PickUpItem; 4] = [
+    PickUpItem::HealingPotion, PickUpItem::BlinkPotion,
+    PickUpItem::SpeedPotion,   PickUpItem::RegenerationPotion,
+];
+#[rustfmt::skip]
+const SCROLLS: [PickUpItem; 3] = [
+    PickUpItem::TeleportScroll, PickUpItem::MagicMappingScroll, PickUpItem::FearScroll,
+];
+
+// A hand-made room, drawn in ASCII:
+// '#' rock, '.' floor, '+' a closed door, '\'' an open one, '^' a hidden trap,
+// '!' a random potion, '?' a random scroll, '%' a food ration, '*' a health boost,
+// '/' an attack boost, '[' a shield boost, ')' a random weapon, ']' a random armour,
+// a letter the monster with that glyph (or just floor, if there's no such monster),
+// and ' ' whatever the level has there.
+// These are the glyphs the curses version draws, except for the health boost:
+// it's drawn as '+' there too, which the templates need for the closed doors.
+// The doors on its outer edge are its entrances: one of them gets joined to the level
+// by a corridor, and the others get walled up.
+#[derive(Clone, Debug, Serialize)]
+pub struct Vault {
+    pub name: String,
+    // The levels it can show up on, both included; all of them without a depth line
+    pub depth: (i32, i32),
+    // All of the same length
+    rows: Vec<Vec<char>>,
+}
+impl Vault {
+    // The template starts with an optional "depth: <from>-<to>" line, followed by its rows
+    pub fn parse(name: &str, source: &str) -> Result<Self, DataError> {
+        let invalid = |problem: &str| DataError::Invalid(format!("vault {}: {}", name, problem));
+
+        let mut depth = (1, i32::MAX);
+        let mut rows: Vec<Vec<char>> = vec![];
+        for line in source.lines() {
+            if let Some(range) = line.strip_prefix("depth:") {
+                depth = range
+                    .split_once('-')
+                    .and_then(|(from, to)| {
+                        Some((from.trim().parse().ok()?, to.trim().parse().ok()?))
+                    })
+                    .ok_or_else(|| invalid("its depth range is malformed"))?;
+            } else if !line.trim().is_empty() {
+                rows.push(line.trim_end().chars().collect());
+            }
+        }
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        for row in rows.iter_mut() {
+            row.resize(width, ' ');
+        }
+        let vault = Vault {
+            name: name.to_string(),
+            depth,
+            rows,
+        };
+
+        if vault.rows.is_empty() {
+            Err(invalid("it's empty"))
+        } else if depth.0 > depth.1 {
+            Err(invalid("its depth range is empty"))
+        } else if let Some(&glyph) = vault
+            .rows
+            .iter()
+            .flatten()
+            .find(|&&glyph| !"#.+'^!?%*/[)] ".contains(glyph) && !glyph.is_ascii_alphabetic())
+        {
+            Err(invalid(&format!("it has the unknown glyph '{}'", glyph)))
+        } else if entrances(&vault.rows).is_empty() {
+            Err(invalid("it has no door on its edge to get in through"))
+        } else {
+            Ok(vault)
+        }
+    }
+    // The rows turned a quarter clockwise `orientation % 4` times, mirrored first from 4 on
+    fn oriented(&self, orientation: usize) -> Vec<Vec<char>> {
+        let mut rows = self.rows.clone();
+        if orientation >= 4 {
+            for row in rows.iter_mut() {
+                row.reverse();
+            }
+        }
+        for _ in 0..(orientation % 4) {
+            rows = (0..rows[0].len())
+                .map(|x| rows.iter().rev().map(|row| row[x]).collect())
+                .collect();
+        }
+
+        rows
+    }
+    // Stamps the vault, turned some random way, into solid rock somewhere on the level,
+    // digging a corridor from one of its entrances to the floor.
+    // Returns the monsters in it, or None if no place for it was found,
+    // which is always the case on levels too small for it.
+    pub fn stamp(
+        &self,
+        tiles: &mut TileArray<Tile>,
+        level: i32,
+        rng: &mut impl Rng,
+    ) -> Option<Vec<Being>> {
+        let trap_kinds: Vec<TrapKind> = TRAP_KINDS
+            .into_iter()
+            .filter(|kind| kind.allowed_on(level))
+            .collect();
+        for _ in 0..30 {
+            let rows = self.oriented(rng.gen_range(0, 8));
+            let (width, height) = (rows[0].len() as i32, rows.len() as i32);
+            if width + 2 > tiles.width() || height + 2 > tiles.height() {
+                continue;
+            }
+            let top_left = Point::new(
+                rng.gen_range(1, tiles.width() - width),
+                rng.gen_range(1, tiles.height() - height),
+            );
+            let cells: Vec<(Point, char)> = rows
+                .iter()
+                .enumerate()
+                .flat_map(|(y, row)| {
+                    row.iter()
+                        .enumerate()
+                        .map(move |(x, &glyph)| (top_left + Point::new(x as i32, y as i32), glyph))
+                })
+                .filter(|&(_, glyph)| glyph != ' ')
+                .collect();
+            let is_solid = cells.iter().all(|&(point, _)| {
+                tiles[point] == Tile::Wall
+                    && point
+                        .iter_neighbors()
+                        .all(|p| tiles.in_bounds(p) && tiles[p] == Tile::Wall)
+            });
+            if !is_solid {
+                continue;
+            }
+
+            let entrances: Vec<(Point, Point)> = entrances(&rows)
+                .into_iter()
+                .map(|(entrance, direction)| (top_left + entrance, direction))
+                .collect();
+            let (entrance, direction) = entrances[rng.gen_range(0, entrances.len())];
+            let corridor = match tiles.dig_corridor(entrance + direction, direction) {
+                Some(corridor) => corridor,
+                None => continue,
+            };
+
+            let mut monsters = vec![];
+            for (point, glyph) in cells {
+                tiles[point] = match glyph {
+                    '#' => Tile::Wall,
+                    '+' => Tile::Door(DoorState::Closed),
+                    '\'' => Tile::Door(DoorState::Open),
+                    '^' => Tile::Trap(Trap::hidden(trap_kinds[rng.gen_range(0, trap_kinds.len())])),
+                    '!' => Tile::Item(POTIONS[rng.gen_range(0, POTIONS.len())]),
+                    '?' => Tile::Item(SCROLLS[rng.gen_range(0, SCROLLS.len())]),
+                    '%' => Tile::Item(PickUpItem::FoodRation),
+                    '*' => Tile::Item(PickUpItem::HealthBoost),
+                    '/' => Tile::Item(PickUpItem::AttackBoost),
+                    '[' => Tile::Item(PickUpItem::ShieldBoost),
+                    ')' => Tile::Item(PickUpItem::Gear(gear_for(GearSlot::Weapon, level, rng))),
+                    ']' => Tile::Item(PickUpItem::Gear(gear_for(GearSlot::Armour, level, rng))),
+                    _ => {
+                        if let Some(index) =
+                            monster_kinds().iter().position(|kind| kind.glyph == glyph)
+                        {
+                            monsters.push(Being::new_of_kind(
+                                BeingKind::Monster(index),
+                                point,
+                                rng,
+                            ));
+                        }
+                        Tile::Room
+                    }
+                };
+            }
+            for &(other, _) in entrances.iter().filter(|&&(other, _)| other != entrance) {
+                tiles[other] = Tile::Wall;
+            }
+            for point in corridor {
+                tiles[point] = Tile::Room;
+            }
+
+            return Some(monsters);
+        }
+
+        None
+    }
+}
+
+// The doors on the edge of the rows, along with the direction leading out of them
+fn entrances(rows: &[Vec<char>]) -> Vec<(Point, Point)> {
+    let (width, height) = (rows[0].len() as i32, rows.len() as i32);
+    let mut entrances = vec![];
+    for (y, row) in rows.iter().enumerate() {
+        for (x, &glyph) in row.iter().enumerate() {
+            let (x, y) = (x as i32, y as i32);
+            let direction = if y == 0 {
+                Point::new(0, -1)
+            } else if y == height - 1 {
+                Point::new(0, 1)
+            } else if x == 0 {
+                Point::new(-1, 0)
+            } else if x == width - 1 {
+                Point::new(1, 0)
+            } else {
+                continue;
+            };
+            if glyph == '+' || glyph == '\'' {
+                entrances.push((Point::new(x, y), direction));
+            }
+        }
+    }
+
+    entrances
+}
+fn gear_for(slot: GearSlot, level: i32, rng: &mut impl Rng) -> Gear {
+    loop {
+        let gear = Gear::generate(level, rng);
+        if gear.slot() == slot {
+            break gear;
+        }
+    }
+}
+
+fn default_vaults() -> Vec<Vault> {
+    DEFAULT_VAULTS
+        .iter()
+        .map(|(name, source)| Vault::parse(name, source).unwrap())
+        .collect()
+}
+pub static VAULTS: DataFile<Vec<Vault>> =
+    DataFile::new(VAULTS_DIR_NAME, default_vaults, read_vaults);
+pub fn vaults() -> &'static [Vault] {
+    VAULTS.get()
+}
+// Adds the templates in the directory to the default vaults, replacing those with the same name
+fn read_vaults(path: &Path) -> Result<Vec<Vault>, DataError> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "txt") {
+            paths.push(path);
+        }
+    }
+    // The order the vaults are picked from has to be the same on every run
+    paths.sort();
+
+    let mut vaults = default_vaults();
+    for path in paths {
+        let name = path
+            .file_stem()
+            .map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+        let vault = Vault::parse(&name, &fs::read_to_string(&path)?)?;
+        match vaults.iter_mut().find(|other| other.name == name) {
+            Some(other) => *other = vault,
+            None => vaults.push(vault),
+        }
+    }
+
+    Ok(vaults)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::{dungeon::dungeon_config, room::Room, GameRng};
+
+    #[test]
+    fn vault_default_vaults() {
+        let vaults = default_vaults();
+        assert_eq!(vaults.len(), DEFAULT_VAULTS.len());
+        for level in 1..=dungeon_config().level_count {
+            assert!(vaults
+                .iter()
+                .any(|vault| (vault.depth.0..=vault.depth.1).contains(&level)));
+        }
+    }
+    #[test]
+    fn vault_oriented() {
+        let vault = Vault::parse("test", "##+\n#.G").unwrap();
+        assert_eq!(vault.depth, (1, i32::MAX));
+
+        let turned = vault.oriented(1);
+        assert_eq!(turned, vec![vec!['#', '#'], vec!['.', '#'], vec!['G', '+']]);
+        assert_eq!(
+            vault.oriented(4),
+            vec![vec!['+', '#', '#'], vec!['G', '.', '#']]
+        );
+        assert_eq!(
+            entrances(&turned),
+            vec![(Point::new(1, 2), Point::new(0, 1))]
+        );
+    }
+    #[test]
+    fn vault_invalid() {
+        assert!(Vault::parse("test", "depth: 2-4\n#+#").is_ok());
+        assert!(matches!(
+            Vault::parse("test", "depth: 4-2\n#+#"),
+            Err(DataError::Invalid(_))
+        ));
+        assert!(matches!(
+            Vault::parse("test", "depth: deep\n#+#"),
+            Err(DataError::Invalid(_))
+        ));
+        assert!(matches!(
+            Vault::parse("test", "#+@"),
+            Err(DataError::Invalid(_))
+        ));
+        assert!(matches!(
+            Vault::parse("test", "###\n#+#\n###"),
+            Err(DataError::Invalid(_))
+        ));
+    }
+    #[test]
+    fn vault_stamp() {
+        let mut rng = GameRng::seed_from_u64(0);
+        let lair = default_vaults()
+            .into_iter()
+            .find(|vault| vault.name == "lair")
+            .unwrap();
+
+        let mut stamped = 0;
+        for _ in 0..20 {
+            let mut tiles = TileArray::new(60, 24, Tile::Wall);
+            tiles.carve_room(Room::new(0, 0, 12, 23));
+            if let Some(monsters) = lair.stamp(&mut tiles, 5, &mut rng) {
+                stamped += 1;
+                assert_eq!(monsters.len(), 3);
+                let reachable = tiles.flood_fill(Point::new(5, 5), |tile| {
+                    tile.is_walkable() || tile.is_door()
+                });
+                assert!(monsters
+                    .iter()
+                    .all(|monster| reachable.contains(&monster.position)));
+            }
+        }
+        assert!(stamped > 0);
+
+        // Every item glyph gets its item, the health boost's included
+        let treasure = Vault::parse("treasure", "#####\n+*/[#\n#####").unwrap();
+        let mut tiles = TileArray::new(60, 24, Tile::Wall);
+        tiles.carve_room(Room::new(0, 0, 12, 23));
+        while treasure.stamp(&mut tiles, 5, &mut rng).is_none() {}
+        for item in [
+            PickUpItem::HealthBoost,
+            PickUpItem::AttackBoost,
+            PickUpItem::ShieldBoost,
+        ] {
+            assert!(tiles.points().any(|point| tiles[point] == Tile::Item(item)));
+        }
+
+        // It doesn't fit in a level this small, whichever way it's turned
+        let mut tiles = TileArray::new(12, 8, Tile::Wall);
+        assert!(lair.stamp(&mut tiles, 5, &mut rng).is_none());
+    }
+}
@@ -1,10 +1,33 @@
+use std::{
+    cmp::{max, min},
+    env,
+    error::Error,
+    fmt::Display,
+    fs,
+    io::ErrorKind,
+};
+
 use common::{
-    event::Event, gamecontext::GameContext, pickupitem::PickUpItem, playeraction::PlayerAction,
-    point::Point, tile::Tile, LEVEL_HEIGHT, LEVEL_WIDTH,
+    being::experience_for_level,
+    dungeon::{self, dungeon_config},
+    event::Event,
+    gamecontext::GameContext,
+    gear::GearSlot,
+    hunger::Hunger,
+    monsterkind::{self, Colour, COLOURS},
+    pickupitem::PickUpItem,
+    playeraction::PlayerAction,
+    point::Point,
+    replay::{Replay, ReplayPlayer, REPLAY_FILE_NAME},
+    savefile::{self, SaveError, SAVE_FILE_NAME},
+    targeting::Targeting,
+    tile::{DoorState, Tile},
+    vault,
 };
 use pancurses::{
-    chtype, endwin, init_pair, initscr, newwin, noecho, start_color, Input, COLOR_BLACK,
-    COLOR_CYAN, COLOR_GREEN, COLOR_MAGENTA, COLOR_PAIR, COLOR_RED, COLOR_WHITE,
+    chtype, endwin, init_pair, initscr, newwin, noecho, start_color, Input, Window, A_REVERSE,
+    COLOR_BLACK, COLOR_BLUE, COLOR_CYAN, COLOR_GREEN, COLOR_MAGENTA, COLOR_PAIR, COLOR_RED,
+    COLOR_WHITE, COLOR_YELLOW,
 };
 
 const HP_COLOR: chtype = 1;
@@ -13,15 +36,22 @@ const SHIELD_COLOR: chtype = 3;
 const WALL_BACKGROUND: chtype = 4;
 const HP_BACKGROUND: chtype = 5;
 const XP_COLOR: chtype = 6;
+const CONSUMABLE_COLOR: chtype = 7;
+// The monsters' colours take the pairs from this one on, in the order of COLOURS
+const MONSTER_COLORS: chtype = 8;
+// How wide the info panel next to the level has to be at least, its box included
+const MIN_INFO_WIDTH: i32 = 20;
 
-struct CursesRAII;
+struct CursesRAII {
+    screen: Window,
+}
 impl CursesRAII {
     fn init() -> Self {
-        initscr();
+        let screen = initscr();
         noecho();
         start_color();
 
-        CursesRAII
+        CursesRAII { screen }
     }
 }
 impl Drop for CursesRAII {
@@ -31,12 +61,31 @@ impl Drop for CursesRAII {
 }
 
 fn main() {
-    let _curses_raii = CursesRAII::init();
+    let curses_raii = CursesRAII::init();
 
-    let game_window = newwin(LEVEL_HEIGHT, LEVEL_WIDTH, 0, 0);
-    let info_frame = newwin(LEVEL_HEIGHT, 0, 0, LEVEL_WIDTH);
+    // The dungeon configuration sets the size of the levels, so it's loaded before the windows
+    if let Err(error) = load_overrides() {
+        show_error(error);
+        return;
+    }
+
+    // The levels are drawn whole, so they have to fit in the terminal
+    let dimensions = dungeon_config().dimensions;
+    let (terminal_height, terminal_width) = curses_raii.screen.get_max_yx();
+    if terminal_height < dimensions.height || terminal_width < dimensions.width + MIN_INFO_WIDTH {
+        show_error(format!(
+            "The terminal is {}x{}, but the levels of this dungeon need at least {}x{}.",
+            terminal_width,
+            terminal_height,
+            dimensions.width + MIN_INFO_WIDTH,
+            dimensions.height
+        ));
+        return;
+    }
+    let game_window = newwin(dimensions.height, dimensions.width, 0, 0);
+    let info_frame = newwin(dimensions.height, 0, 0, dimensions.width);
     let info_window = info_frame
-        .derwin(LEVEL_HEIGHT - 2, info_frame.get_max_x() - 2, 1, 1)
+        .derwin(dimensions.height - 2, info_frame.get_max_x() - 2, 1, 1)
         .unwrap();
 
     game_window.keypad(true);
@@ -49,113 +98,58 @@ fn main() {
     init_pair(WALL_BACKGROUND as i16, COLOR_BLACK, COLOR_WHITE);
     init_pair(HP_BACKGROUND as i16, COLOR_BLACK, COLOR_RED);
     init_pair(XP_COLOR as i16, COLOR_GREEN, COLOR_BLACK);
-
-    let mut context = GameContext::new();
-
-    'game: loop {
-        info_window.clear();
-        game_window.clear();
-
-        info_window.attron(COLOR_PAIR(HP_BACKGROUND));
-        info_window.mvaddstr(
-            0,
-            0,
-            format!(
-                "HP: {}/{}\n",
-                context.player.health_points, context.player.max_health_points
-            ),
+    init_pair(CONSUMABLE_COLOR as i16, COLOR_YELLOW, COLOR_BLACK);
+    for colour in COLOURS {
+        init_pair(
+            (MONSTER_COLORS + colour as chtype) as i16,
+            curses_color_of(colour),
+            COLOR_BLACK,
         );
-        info_window.attroff(COLOR_PAIR(HP_BACKGROUND));
-
-        info_window.attron(COLOR_PAIR(XP_COLOR));
-        info_window.addstr(format!("XP: {}\n", context.player.experience_points));
-        info_window.attroff(COLOR_PAIR(XP_COLOR));
-
-        info_window.attron(COLOR_PAIR(ATTACK_COLOR));
-        info_window.addstr(format!("Attack: 0-{}\n", context.player.max_attack));
-        info_window.attroff(COLOR_PAIR(ATTACK_COLOR));
-
-        info_window.attron(COLOR_PAIR(SHIELD_COLOR));
-        info_window.addstr(format!("Shielding: 0-{}\n\n", context.player.max_shield));
-        info_window.attroff(COLOR_PAIR(SHIELD_COLOR));
-
-        info_window.addstr(format!(
-            "Level {}\nTurn {}\n\n",
-            context.current_level, context.current_turn
-        ));
-
-        for event in &context.events {
-            match event {
-                Event::Died(_) | Event::Won(_) => {
-                    game_window.mvaddstr(0, 0, event.message(context.current_level));
-                    game_window.addstr("Press any key to exit the game...");
-                    game_window.refresh();
-                    info_window.refresh();
+    }
 
-                    game_window.getch();
-                    break 'game;
+    let args: Vec<String> = env::args().collect();
+    if let [_, flag, path] = &args[..] {
+        if flag == "--replay" {
+            match Replay::load(path) {
+                Ok(replay) => {
+                    watch_replay(ReplayPlayer::new(replay), &game_window, &info_window);
                 }
-                _ => {
-                    info_window.addstr(format!("* {}", event.message(context.current_level)));
+                Err(error) => {
+                    game_window.mvaddstr(0, 0, format!("{}\n", error));
+                    game_window.addstr("Press any key to exit...");
+                    game_window.getch();
                 }
             }
+            return;
         }
+    }
 
-        game_window.mv(0, 0);
-        for y in 0..LEVEL_HEIGHT {
-            for x in 0..LEVEL_WIDTH {
-                match (context.level.last_seen[(y, x)], context.level.tiles[(y, x)]) {
-                    (t, Tile::Room) if t == context.current_turn => {
-                        game_window.addch('.');
-                    }
-                    (t, Tile::Item(item)) if t > 0 => match item {
-                        PickUpItem::HealthBoost => {
-                            game_window.attron(COLOR_PAIR(HP_COLOR));
-                            game_window.addch('+');
-                            game_window.attroff(COLOR_PAIR(HP_COLOR));
-                        }
-                        PickUpItem::AttackBoost => {
-                            game_window.attron(COLOR_PAIR(ATTACK_COLOR));
-                            game_window.addch('/');
-                            game_window.attroff(COLOR_PAIR(ATTACK_COLOR));
-                        }
-                        PickUpItem::ShieldBoost => {
-                            game_window.attron(COLOR_PAIR(SHIELD_COLOR));
-                            game_window.addch('[');
-                            game_window.attroff(COLOR_PAIR(SHIELD_COLOR));
-                        }
-                    },
-                    (t, Tile::Wall) if t > 0 => {
-                        game_window.attron(COLOR_PAIR(WALL_BACKGROUND));
-                        game_window.addch(' ');
-                        game_window.attroff(COLOR_PAIR(WALL_BACKGROUND));
-                    }
-                    (t, Tile::Door) if t > 0 => {
-                        game_window.attron(COLOR_PAIR(WALL_BACKGROUND));
-                        game_window.addch('+');
-                        game_window.attroff(COLOR_PAIR(WALL_BACKGROUND));
-                    }
-                    (_, Tile::Stairs(-1)) => {
-                        game_window.addch('<');
-                    }
-                    (t, Tile::Stairs(1)) if t > 0 => {
-                        game_window.addch(if context.current_level < 25 { '>' } else { '0' });
-                    }
-                    _ => {
-                        game_window.addch(' ');
-                    }
-                };
-            }
+    // A save is resumed only once, so that it can't be used to undo a death.
+    let mut context = match savefile::load(SAVE_FILE_NAME) {
+        Ok(context) => {
+            let _ = fs::remove_file(SAVE_FILE_NAME);
+            context
         }
-        for enemy in &context.level.enemies {
-            if context.level.last_seen[enemy.position] == context.current_turn {
-                if let Some(char) = enemy.kind.to_string().chars().next() {
-                    game_window.mvaddch(enemy.position.y, enemy.position.x, char);
-                }
-            }
+        Err(SaveError::Io(error)) if error.kind() == ErrorKind::NotFound => GameContext::new(),
+        // Starting anew would overwrite the save on the next save and quit
+        Err(error) => {
+            show_error(format!(
+                "{}\nMove {} away to start a new game.",
+                error, SAVE_FILE_NAME
+            ));
+            return;
+        }
+    };
+
+    'game: loop {
+        draw_info(&context, &info_window);
+
+        if let Some(event) = end_event(&context) {
+            show_end(event, &context, &game_window, &info_window);
+            break 'game;
         }
-        game_window.mvaddch(context.player.position.y, context.player.position.x, '@');
-        game_window.mv(context.player.position.y, context.player.position.x);
+
+        draw_level(&context, &game_window);
 
         info_window.refresh();
         game_window.refresh();
@@ -164,24 +158,57 @@ fn main() {
             Some(Input::Character('q')) => {
                 game_window.clear();
                 game_window.mvaddstr(0, 0, "Are you sure you want to leave the game?\n");
-                game_window.addstr("Any progress will be lost! [y/N]:");
+                game_window.addstr("Any progress will be lost! Press S to save instead. [y/N]:");
                 game_window.refresh();
 
                 if let Some(Input::Character('y' | 'Y')) = game_window.getch() {
                     break 'game;
                 }
             }
-            Some(Input::KeyUp | Input::Character('k')) => {
-                context.next_turn(PlayerAction::MoveBy(Point::new(0, -1)));
+            Some(Input::Character('S')) => {
+                game_window.clear();
+                if let Err(error) = savefile::save(&context, SAVE_FILE_NAME) {
+                    game_window.mvaddstr(0, 0, format!("{}\n", error));
+                    game_window.addstr("Press any key to go back to the game...");
+                    game_window.refresh();
+                    game_window.getch();
+                } else {
+                    break 'game;
+                }
+            }
+            Some(Input::Character('g' | ',')) => {
+                context.next_turn(PlayerAction::PickUp);
+            }
+            Some(Input::Character('s')) => {
+                context.next_turn(PlayerAction::Search);
             }
-            Some(Input::KeyDown | Input::Character('j')) => {
-                context.next_turn(PlayerAction::MoveBy(Point::new(0, 1)));
+            Some(Input::Character('o')) => {
+                if let Some(direction) = choose_direction("Open", &game_window, &info_window) {
+                    context.next_turn(PlayerAction::OpenDoor(direction));
+                }
             }
-            Some(Input::KeyLeft | Input::Character('h')) => {
-                context.next_turn(PlayerAction::MoveBy(Point::new(-1, 0)));
+            Some(Input::Character('c')) => {
+                if let Some(direction) = choose_direction("Close", &game_window, &info_window) {
+                    context.next_turn(PlayerAction::CloseDoor(direction));
+                }
             }
-            Some(Input::KeyRight | Input::Character('l')) => {
-                context.next_turn(PlayerAction::MoveBy(Point::new(1, 0)));
+            // u is taken by the diagonal moves, so items get applied instead
+            Some(Input::Character('a')) => {
+                if let Some(index) = choose_item(&context, "Use", &game_window, &info_window) {
+                    context.next_turn(PlayerAction::UseItem(index));
+                }
+            }
+            Some(Input::Character('d')) => {
+                if let Some(index) = choose_item(&context, "Drop", &game_window, &info_window) {
+                    context.next_turn(PlayerAction::DropItem(index));
+                }
+            }
+            Some(Input::Character('t')) => {
+                if let Some(index) = choose_item(&context, "Throw", &game_window, &info_window) {
+                    if let Some(target) = choose_target(&context, &game_window, &info_window) {
+                        context.next_turn(PlayerAction::ThrowItem(index, target));
+                    }
+                }
             }
             Some(Input::Character(' ')) => {
                 context.next_turn(PlayerAction::MoveBy(Point::new(0, 0)));
@@ -189,7 +216,371 @@ fn main() {
             Some(Input::KeyEnter | Input::Character('\n')) => {
                 context.next_turn(PlayerAction::Select);
             }
+            Some(Input::Character('W')) => {
+                context.next_turn(PlayerAction::Unequip(GearSlot::Weapon));
+            }
+            Some(Input::Character('A')) => {
+                context.next_turn(PlayerAction::Unequip(GearSlot::Armour));
+            }
+            Some(input) => {
+                if let Some(direction) = direction_of(input) {
+                    context.next_turn(PlayerAction::MoveBy(direction));
+                }
+            }
+            None => {}
+        }
+    }
+
+    let _ = context.replay().save(REPLAY_FILE_NAME);
+}
+
+// Replaces the monsters, the dungeon configuration and the vaults with the files next to the game
+fn load_overrides() -> Result<(), Box<dyn Error>> {
+    monsterkind::MONSTER_KINDS.load_override()?;
+    dungeon::DUNGEON_CONFIG.load_override()?;
+    vault::VAULTS.load_override()?;
+
+    Ok(())
+}
+
+fn watch_replay(mut replay_player: ReplayPlayer, game_window: &Window, info_window: &Window) {
+    let mut paused = false;
+    let mut delay = 300;
+
+    loop {
+        let context = &replay_player.context;
+        let (played, total) = replay_player.progress();
+
+        draw_info(context, info_window);
+        info_window.addstr(format!(
+            "\nReplay: {}/{} actions{}\n",
+            played,
+            total,
+            if paused { " (paused)" } else { "" }
+        ));
+        info_window.addstr("Space = pause, . = step\n+/- = speed, q = quit\n");
+
+        if let Some(event) = end_event(context) {
+            show_end(event, context, game_window, info_window);
+            break;
+        }
+
+        draw_level(context, game_window);
+
+        info_window.refresh();
+        game_window.refresh();
+
+        game_window.timeout(if paused || replay_player.is_finished() {
+            -1
+        } else {
+            delay
+        });
+        match game_window.getch() {
+            Some(Input::Character('q')) => {
+                break;
+            }
+            Some(Input::Character(' ')) => {
+                paused = !paused;
+            }
+            Some(Input::Character('.') | Input::KeyRight) => {
+                replay_player.step();
+            }
+            Some(Input::Character('+')) => {
+                delay = max(25, delay / 2);
+            }
+            Some(Input::Character('-')) => {
+                delay = min(2400, delay * 2);
+            }
+            None if !paused => {
+                replay_player.step();
+            }
             _ => {}
         }
     }
 }
+
+fn item_appearance(item: PickUpItem) -> (char, chtype) {
+    match item {
+        PickUpItem::HealthBoost => ('+', HP_COLOR),
+        PickUpItem::AttackBoost => ('/', ATTACK_COLOR),
+        PickUpItem::ShieldBoost => ('[', SHIELD_COLOR),
+        PickUpItem::HealingPotion => ('!', HP_COLOR),
+        PickUpItem::BlinkPotion | PickUpItem::SpeedPotion | PickUpItem::RegenerationPotion => {
+            ('!', CONSUMABLE_COLOR)
+        }
+        PickUpItem::TeleportScroll | PickUpItem::MagicMappingScroll | PickUpItem::FearScroll => {
+            ('?', CONSUMABLE_COLOR)
+        }
+        PickUpItem::FoodRation => ('%', CONSUMABLE_COLOR),
+        PickUpItem::Key => ('-', CONSUMABLE_COLOR),
+        PickUpItem::Gear(gear) => match gear.slot() {
+            GearSlot::Weapon => (')', ATTACK_COLOR),
+            GearSlot::Armour => (']', SHIELD_COLOR),
+        },
+    }
+}
+
+fn curses_color_of(colour: Colour) -> i16 {
+    match colour {
+        Colour::Red => COLOR_RED,
+        Colour::Green => COLOR_GREEN,
+        Colour::Yellow => COLOR_YELLOW,
+        Colour::Blue => COLOR_BLUE,
+        Colour::Magenta => COLOR_MAGENTA,
+        Colour::Cyan => COLOR_CYAN,
+        Colour::White => COLOR_WHITE,
+    }
+}
+
+fn direction_of(input: Input) -> Option<Point> {
+    match input {
+        Input::KeyUp | Input::Character('k') => Some(Point::new(0, -1)),
+        Input::KeyDown | Input::Character('j') => Some(Point::new(0, 1)),
+        Input::KeyLeft | Input::Character('h') => Some(Point::new(-1, 0)),
+        Input::KeyRight | Input::Character('l') => Some(Point::new(1, 0)),
+        Input::KeyA1 | Input::KeyHome | Input::Character('y') => Some(Point::new(-1, -1)),
+        Input::KeyA3 | Input::KeyPPage | Input::Character('u') => Some(Point::new(1, -1)),
+        Input::KeyC1 | Input::KeyEnd | Input::Character('b') => Some(Point::new(-1, 1)),
+        Input::KeyC3 | Input::KeyNPage | Input::Character('n') => Some(Point::new(1, 1)),
+        _ => None,
+    }
+}
+
+// Asks the player to pick an item from the inventory, returning its index.
+fn choose_item(
+    context: &GameContext,
+    verb: &str,
+    game_window: &Window,
+    info_window: &Window,
+) -> Option<usize> {
+    let item_count = context.player.inventory.len();
+    if item_count == 0 {
+        return None;
+    }
+
+    info_window.addstr(format!("\n{} which item? [1-{}] ", verb, item_count));
+    info_window.refresh();
+
+    match game_window.getch() {
+        Some(Input::Character(char)) => char
+            .to_digit(10)
+            .map(|digit| digit as usize)
+            .filter(|&digit| digit >= 1 && digit <= item_count)
+            .map(|digit| digit - 1),
+        _ => None,
+    }
+}
+
+// Asks the player which way the door they want to act on is.
+fn choose_direction(verb: &str, game_window: &Window, info_window: &Window) -> Option<Point> {
+    info_window.addstr(format!("\n{} the door in which direction? ", verb));
+    info_window.refresh();
+
+    game_window.getch().and_then(direction_of)
+}
+
+// Lets the player move a targeting cursor around, returning the chosen target.
+fn choose_target(
+    context: &GameContext,
+    game_window: &Window,
+    info_window: &Window,
+) -> Option<Point> {
+    let mut targeting = Targeting::new(context);
+
+    loop {
+        draw_info(context, info_window);
+        info_window.addstr("\nTab = next enemy, arrows = move\nEnter / t = throw, Esc = cancel\n");
+
+        draw_level(context, game_window);
+        for point in targeting.path(context) {
+            if point != targeting.target {
+                game_window.mvaddch(point.y, point.x, '*');
+            }
+        }
+        game_window.mvchgat(targeting.target.y, targeting.target.x, 1, A_REVERSE, 0);
+        game_window.mv(targeting.target.y, targeting.target.x);
+
+        info_window.refresh();
+        game_window.refresh();
+
+        match game_window.getch() {
+            Some(Input::Character('\t')) => {
+                targeting.next_enemy(context);
+            }
+            Some(Input::KeyEnter | Input::Character('\n' | 't')) => {
+                return Some(targeting.target);
+            }
+            Some(Input::Character('\u{1b}' | 'q')) => {
+                return None;
+            }
+            Some(input) => {
+                if let Some(direction) = direction_of(input) {
+                    targeting.move_by(context, direction);
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+fn end_event(context: &GameContext) -> Option<Event> {
+    context
+        .events
+        .iter()
+        .copied()
+        .find(|event| matches!(event, Event::Died(_) | Event::Won(_)))
+}
+
+fn show_end(event: Event, context: &GameContext, game_window: &Window, info_window: &Window) {
+    game_window.timeout(-1);
+    game_window.mvaddstr(0, 0, event.message(context.current_level));
+    game_window.addstr("Press any key to exit the game...");
+    game_window.refresh();
+    info_window.refresh();
+
+    game_window.getch();
+}
+
+fn show_error(error: impl Display) {
+    let message_window = newwin(0, 0, 0, 0);
+    message_window.mvaddstr(0, 0, format!("{}\n", error));
+    message_window.addstr("Press any key to exit...");
+    message_window.getch();
+}
+
+fn draw_info(context: &GameContext, info_window: &Window) {
+    info_window.clear();
+
+    info_window.attron(COLOR_PAIR(HP_BACKGROUND));
+    info_window.mvaddstr(
+        0,
+        0,
+        format!(
+            "HP: {}/{}\n",
+            context.player.health_points, context.player.max_health_points
+        ),
+    );
+    info_window.attroff(COLOR_PAIR(HP_BACKGROUND));
+
+    info_window.attron(COLOR_PAIR(XP_COLOR));
+    info_window.addstr(format!(
+        "XP: {}/{} (level {})\n",
+        context.player.experience_points,
+        experience_for_level(context.player.experience_level + 1),
+        context.player.experience_level
+    ));
+    info_window.attroff(COLOR_PAIR(XP_COLOR));
+
+    info_window.attron(COLOR_PAIR(CONSUMABLE_COLOR));
+    let hunger = Hunger::of(context.player.nutrition);
+    if hunger != Hunger::Fed {
+        info_window.addstr(format!("{}\n", hunger));
+    }
+    for status in context.player.statuses.iter() {
+        info_window.addstr(format!("{}\n", status));
+    }
+    info_window.attroff(COLOR_PAIR(CONSUMABLE_COLOR));
+
+    info_window.attron(COLOR_PAIR(ATTACK_COLOR));
+    info_window.addstr(format!("Attack: 0-{}\n", context.player.max_attack));
+    if let Some(weapon) = context.player.equipment.weapon {
+        info_window.addstr(format!("Weapon: {}\n", weapon));
+    }
+    info_window.attroff(COLOR_PAIR(ATTACK_COLOR));
+
+    info_window.attron(COLOR_PAIR(SHIELD_COLOR));
+    info_window.addstr(format!("Shielding: 0-{}\n", context.player.max_shield));
+    if let Some(armour) = context.player.equipment.armour {
+        info_window.addstr(format!("Armour: {}\n", armour));
+    }
+    info_window.attroff(COLOR_PAIR(SHIELD_COLOR));
+    info_window.addstr("\n");
+
+    info_window.addstr(format!(
+        "Level {}\nTurn {}\n\n",
+        context.current_level, context.current_turn
+    ));
+
+    if !context.player.inventory.is_empty() {
+        info_window.addstr("Inventory:\n");
+        for (i, item) in context.player.inventory.iter().enumerate() {
+            info_window.addstr(format!("{}. {}\n", i + 1, item));
+        }
+        info_window.addstr("\n");
+    }
+
+    for event in &context.events {
+        if let Event::Died(_) | Event::Won(_) = event {
+            continue;
+        }
+        info_window.addstr(format!("* {}", event.message(context.current_level)));
+    }
+}
+
+fn draw_level(context: &GameContext, game_window: &Window) {
+    game_window.clear();
+
+    game_window.mv(0, 0);
+    for y in 0..context.level.tiles.height() {
+        for x in 0..context.level.tiles.width() {
+            match (
+                context.level.last_seen[(y, x)],
+                context.level.tiles[(y, x)].as_seen(),
+            ) {
+                (t, Tile::Room) if t == context.current_turn => {
+                    game_window.addch('.');
+                }
+                (t, Tile::Item(item)) if t > 0 => {
+                    let (char, color) = item_appearance(item);
+                    game_window.attron(COLOR_PAIR(color));
+                    game_window.addch(char);
+                    game_window.attroff(COLOR_PAIR(color));
+                }
+                (t, Tile::Wall) if t > 0 => {
+                    game_window.attron(COLOR_PAIR(WALL_BACKGROUND));
+                    game_window.addch(' ');
+                    game_window.attroff(COLOR_PAIR(WALL_BACKGROUND));
+                }
+                (t, Tile::Trap(_)) if t > 0 => {
+                    game_window.attron(COLOR_PAIR(HP_COLOR));
+                    game_window.addch('^');
+                    game_window.attroff(COLOR_PAIR(HP_COLOR));
+                }
+                (t, Tile::Door(DoorState::Open)) if t > 0 => {
+                    game_window.attron(COLOR_PAIR(WALL_BACKGROUND));
+                    game_window.addch('\'');
+                    game_window.attroff(COLOR_PAIR(WALL_BACKGROUND));
+                }
+                (t, Tile::Door(DoorState::Closed)) if t > 0 => {
+                    game_window.attron(COLOR_PAIR(WALL_BACKGROUND));
+                    game_window.addch('+');
+                    game_window.attroff(COLOR_PAIR(WALL_BACKGROUND));
+                }
+                (t, Tile::Door(DoorState::Locked)) if t > 0 => {
+                    game_window.attron(COLOR_PAIR(CONSUMABLE_COLOR));
+                    game_window.addch('+');
+                    game_window.attroff(COLOR_PAIR(CONSUMABLE_COLOR));
+                }
+                (_, Tile::Stairs(-1)) => {
+                    game_window.addch('<');
+                }
+                (t, Tile::Stairs(1)) if t > 0 => {
+                    game_window.addch(if context.is_last_level() { '0' } else { '>' });
+                }
+                _ => {
+                    game_window.addch(' ');
+                }
+            };
+        }
+    }
+    for enemy in &context.level.enemies {
+        if context.level.last_seen[enemy.position] == context.current_turn {
+            let color = MONSTER_COLORS + enemy.kind.colour() as chtype;
+            game_window.attron(COLOR_PAIR(color));
+            game_window.mvaddch(enemy.position.y, enemy.position.x, enemy.kind.glyph());
+            game_window.attroff(COLOR_PAIR(color));
+        }
+    }
+    game_window.mvaddch(context.player.position.y, context.player.position.x, '@');
+    game_window.mv(context.player.position.y, context.player.position.x);
+}
@@ -1,7 +1,25 @@
 use common::event::Event;
+use common::targeting::Targeting;
 
 #[derive(Clone, Copy, Debug)]
 pub enum AppState {
     InGame,
+    ChoosingItem(ItemCommand),
+    ChoosingDirection(DoorCommand),
+    // Targeting(index of the item to throw, targeting cursor)
+    Targeting(usize, Targeting),
     ShowingEnd(Event),
 }
+
+#[derive(Clone, Copy, Debug)]
+pub enum ItemCommand {
+    Use,
+    Drop,
+    Throw,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum DoorCommand {
+    Open,
+    Close,
+}
@@ -1,36 +1,67 @@
-use common::event::Event::{Attacked, Died, GotAttacked, Won};
+use std::cmp::{max, min};
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+use std::{env, fs};
+
+use common::dungeon::{self, dungeon_config};
+use common::event::Event::{Attacked, Died, GotAttacked, GotShot, LeveledUp, Won};
 use common::gamecontext::GameContext;
-use common::pickupitem::PickUpItem;
+use common::gear::GearSlot;
+use common::monsterkind;
 use common::playeraction::PlayerAction;
 use common::point::Point;
-use common::tile::Tile;
-use common::{LEVEL_HEIGHT, LEVEL_WIDTH};
+use common::replay::{Replay, ReplayPlayer, REPLAY_FILE_NAME};
+use common::savefile::{self, SaveError, SAVE_FILE_NAME};
+use common::targeting::Targeting;
+use common::vault;
 use sdl2::controller::Button;
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::keyboard::{Keycode, Mod};
 use sdl2::pixels::Color;
-use sdl2::rect::Rect;
 use sdl2::render::BlendMode::Blend;
+use sdl2::render::{Canvas, RenderTarget, TextureCreator};
 use sdl2::rwops::RWops;
+use sdl2::ttf::Font;
+use sdl2::video::WindowContext;
+use sdl2::EventPump;
 
-use crate::appstate::AppState;
-use crate::rendertext::{render_char, render_text};
+use crate::appstate::{AppState, DoorCommand, ItemCommand};
+use crate::rendergame::{render_end, render_game, render_targeting};
+use crate::rendertext::render_text;
 
 mod appstate;
+mod rendergame;
 mod rendertext;
 
-pub const SCREEN_WIDTH: u32 = 1280;
-pub const SCREEN_HEIGHT: u32 = 720;
+// The window fits the level with the info panel next to it, and is at least this tall
+pub const INFO_PANEL_WIDTH: u32 = 380;
+pub const MIN_SCREEN_HEIGHT: u32 = 720;
 pub const TILE_WIDTH: i32 = 15;
 pub const TILE_HEIGHT: i32 = 30;
 
 fn main() {
+    if let Err(error) = monsterkind::MONSTER_KINDS.load_override() {
+        eprintln!("{}", error);
+        return;
+    }
+    if let Err(error) = dungeon::DUNGEON_CONFIG.load_override() {
+        eprintln!("{}", error);
+        return;
+    }
+    if let Err(error) = vault::VAULTS.load_override() {
+        eprintln!("{}", error);
+        return;
+    }
+
     let sdl_context = sdl2::init().unwrap();
     let mut event_pump = sdl_context.event_pump().unwrap();
 
+    let dimensions = dungeon_config().dimensions;
+    let screen_width = (TILE_WIDTH * dimensions.width) as u32 + INFO_PANEL_WIDTH;
+    let screen_height = ((TILE_HEIGHT * dimensions.height) as u32).max(MIN_SCREEN_HEIGHT);
     let sdl_video = sdl_context.video().unwrap();
     let window = sdl_video
-        .window("MyRustRoguelike", SCREEN_WIDTH, SCREEN_HEIGHT)
+        .window("MyRustRoguelike", screen_width, screen_height)
         .position_centered()
         .build()
         .unwrap();
@@ -45,7 +76,39 @@ fn main() {
     let sdl_controller = sdl_context.game_controller().unwrap();
     let mut controller_0 = sdl_controller.open(0).ok();
 
-    let mut context = GameContext::new();
+    let args: Vec<String> = env::args().collect();
+    if let [_, flag, path] = &args[..] {
+        if flag == "--replay" {
+            match Replay::load(path) {
+                Ok(replay) => watch_replay(
+                    ReplayPlayer::new(replay),
+                    &mut event_pump,
+                    &font,
+                    &mut canvas,
+                    &texture_creator,
+                ),
+                Err(error) => eprintln!("{}", error),
+            }
+            return;
+        }
+    }
+
+    // A save is resumed only once, so that it can't be used to undo a death.
+    let mut context = match savefile::load(SAVE_FILE_NAME) {
+        Ok(context) => {
+            let _ = fs::remove_file(SAVE_FILE_NAME);
+            context
+        }
+        Err(SaveError::Io(error)) if error.kind() == ErrorKind::NotFound => GameContext::new(),
+        // Starting anew would overwrite the save on the next save and quit
+        Err(error) => {
+            eprintln!(
+                "{}\nMove {} away to start a new game.",
+                error, SAVE_FILE_NAME
+            );
+            return;
+        }
+    };
     let mut app_state = AppState::InGame;
 
     'game: loop {
@@ -61,23 +124,7 @@ fn main() {
                 }
             }
 
-            canvas.set_draw_color(Color::BLACK);
-            canvas.clear();
-
-            let mut game_window = Rect::new(0, 0, SCREEN_WIDTH, SCREEN_HEIGHT);
-
-            let _ = render_text(
-                &format!(
-                    "{}{}",
-                    event.message(context.current_level),
-                    "Press any key to exit the game..."
-                ),
-                Color::WHITE,
-                &mut game_window,
-                &font,
-                &mut canvas,
-                &texture_creator,
-            );
+            render_end(event, &context, &font, &mut canvas, &texture_creator);
 
             canvas.present();
 
@@ -87,14 +134,154 @@ fn main() {
         let mut context_has_changed = false;
 
         for event in event_pump.poll_iter() {
+            // The window can be closed whatever the player is in the middle of
+            if let Event::Quit { .. } = event {
+                break 'game;
+            }
+            if let AppState::ChoosingItem(command) = app_state {
+                if let Event::KeyDown {
+                    keycode: Some(keycode),
+                    ..
+                } = event
+                {
+                    app_state = AppState::InGame;
+                    if let Some(index) = item_index_of(keycode)
+                        .filter(|&index| index < context.player.inventory.len())
+                    {
+                        match command {
+                            ItemCommand::Use => {
+                                context.next_turn(PlayerAction::UseItem(index));
+                                context_has_changed = true;
+                            }
+                            ItemCommand::Drop => {
+                                context.next_turn(PlayerAction::DropItem(index));
+                                context_has_changed = true;
+                            }
+                            ItemCommand::Throw => {
+                                app_state = AppState::Targeting(index, Targeting::new(&context));
+                            }
+                        }
+                    }
+                }
+                continue;
+            }
+            if let AppState::ChoosingDirection(command) = app_state {
+                if let Event::KeyDown {
+                    keycode: Some(keycode),
+                    ..
+                } = event
+                {
+                    app_state = AppState::InGame;
+                    if let Some(direction) = direction_of(keycode) {
+                        context.next_turn(match command {
+                            DoorCommand::Open => PlayerAction::OpenDoor(direction),
+                            DoorCommand::Close => PlayerAction::CloseDoor(direction),
+                        });
+                        context_has_changed = true;
+                    }
+                }
+                continue;
+            }
+            if let AppState::Targeting(index, mut targeting) = app_state {
+                if let Event::KeyDown {
+                    keycode: Some(keycode),
+                    ..
+                } = event
+                {
+                    match keycode {
+                        Keycode::Tab => {
+                            targeting.next_enemy(&context);
+                            app_state = AppState::Targeting(index, targeting);
+                        }
+                        Keycode::Return | Keycode::KpEnter | Keycode::T => {
+                            app_state = AppState::InGame;
+                            context.next_turn(PlayerAction::ThrowItem(index, targeting.target));
+                            context_has_changed = true;
+                        }
+                        Keycode::Escape | Keycode::Q => {
+                            app_state = AppState::InGame;
+                        }
+                        _ => {
+                            if let Some(direction) = direction_of(keycode) {
+                                targeting.move_by(&context, direction);
+                                app_state = AppState::Targeting(index, targeting);
+                            }
+                        }
+                    }
+                }
+                continue;
+            }
+
             let context_already_changed = context_has_changed;
             context_has_changed = true;
             match event {
-                Event::Quit { .. } => {
-                    break 'game;
+                // Shift is required so that a stray keypress can't end the session
+                Event::KeyDown {
+                    keycode: Some(Keycode::S),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => {
+                    match savefile::save(&context, SAVE_FILE_NAME) {
+                        Ok(()) => {
+                            break 'game;
+                        }
+                        Err(error) => {
+                            eprintln!("{}", error);
+                        }
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::W),
+                    ..
+                } => {
+                    context.next_turn(PlayerAction::Unequip(GearSlot::Weapon));
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::A),
+                    ..
+                } => {
+                    context.next_turn(PlayerAction::Unequip(GearSlot::Armour));
                 }
                 Event::KeyDown {
-                    keycode: Some(Keycode::Up),
+                    keycode: Some(Keycode::G | Keycode::Comma),
+                    ..
+                } => {
+                    context.next_turn(PlayerAction::PickUp);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F),
+                    ..
+                }
+                | Event::ControllerButtonDown {
+                    button: Button::Y, ..
+                } => {
+                    context.next_turn(PlayerAction::Search);
+                }
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::O | Keycode::C)),
+                    ..
+                } => {
+                    context_has_changed = context_already_changed;
+                    app_state = AppState::ChoosingDirection(match keycode {
+                        Keycode::O => DoorCommand::Open,
+                        _ => DoorCommand::Close,
+                    });
+                }
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::U | Keycode::D | Keycode::T)),
+                    ..
+                } => {
+                    context_has_changed = context_already_changed;
+                    if !context.player.inventory.is_empty() {
+                        app_state = AppState::ChoosingItem(match keycode {
+                            Keycode::U => ItemCommand::Use,
+                            Keycode::D => ItemCommand::Drop,
+                            _ => ItemCommand::Throw,
+                        });
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Up | Keycode::Kp8),
                     ..
                 }
                 | Event::ControllerButtonDown {
@@ -104,7 +291,7 @@ fn main() {
                     context.next_turn(PlayerAction::MoveBy(Point::new(0, -1)));
                 }
                 Event::KeyDown {
-                    keycode: Some(Keycode::Down),
+                    keycode: Some(Keycode::Down | Keycode::Kp2),
                     ..
                 }
                 | Event::ControllerButtonDown {
@@ -114,7 +301,7 @@ fn main() {
                     context.next_turn(PlayerAction::MoveBy(Point::new(0, 1)));
                 }
                 Event::KeyDown {
-                    keycode: Some(Keycode::Left),
+                    keycode: Some(Keycode::Left | Keycode::Kp4),
                     ..
                 }
                 | Event::ControllerButtonDown {
@@ -124,7 +311,7 @@ fn main() {
                     context.next_turn(PlayerAction::MoveBy(Point::new(-1, 0)));
                 }
                 Event::KeyDown {
-                    keycode: Some(Keycode::Right),
+                    keycode: Some(Keycode::Right | Keycode::Kp6),
                     ..
                 }
                 | Event::ControllerButtonDown {
@@ -134,7 +321,16 @@ fn main() {
                     context.next_turn(PlayerAction::MoveBy(Point::new(1, 0)));
                 }
                 Event::KeyDown {
-                    keycode: Some(Keycode::Space),
+                    keycode:
+                        Some(keycode @ (Keycode::Kp7 | Keycode::Kp9 | Keycode::Kp1 | Keycode::Kp3)),
+                    ..
+                } => {
+                    if let Some(direction) = direction_of(keycode) {
+                        context.next_turn(PlayerAction::MoveBy(direction));
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Space | Keycode::Kp5),
                     ..
                 }
                 | Event::ControllerButtonDown {
@@ -157,159 +353,194 @@ fn main() {
             }
         }
 
-        canvas.set_draw_color(Color::BLACK);
-        canvas.clear();
-
-        canvas.set_draw_color(Color::WHITE);
-        let _ = canvas.draw_line(
-            (TILE_WIDTH * LEVEL_WIDTH, 0),
-            (TILE_WIDTH * LEVEL_WIDTH, SCREEN_HEIGHT as i32),
-        );
-
-        let mut info_window = Rect::new(
-            TILE_WIDTH * LEVEL_WIDTH + 1,
-            0,
-            SCREEN_WIDTH - (TILE_WIDTH * LEVEL_WIDTH) as u32 - 1,
-            SCREEN_HEIGHT,
-        );
-
-        let _ = render_text(
-            &format!(
-                "Level {}\nTurn {}",
-                context.current_level, context.current_turn
-            ),
-            Color::WHITE,
-            &mut info_window,
-            &font,
-            &mut canvas,
-            &texture_creator,
-        );
-        let _ = render_text(
-            &format!(
-                "HP: {}/{}",
-                context.player.health_points, context.player.max_health_points
-            ),
-            Color::RED,
-            &mut info_window,
-            &font,
-            &mut canvas,
-            &texture_creator,
-        );
-        let _ = render_text(
-            &format!("XP: {}", context.player.experience_points),
-            Color::GREEN,
-            &mut info_window,
-            &font,
-            &mut canvas,
-            &texture_creator,
-        );
-        let _ = render_text(
-            &format!("Attack: 0-{}", context.player.max_attack),
-            Color::MAGENTA,
-            &mut info_window,
-            &font,
-            &mut canvas,
-            &texture_creator,
-        );
-        let _ = render_text(
-            &format!("Shielding: 0-{}", context.player.max_shield),
-            Color::CYAN,
-            &mut info_window,
-            &font,
-            &mut canvas,
-            &texture_creator,
-        );
-
         for event in &context.events {
-            if let GotAttacked(_, _) | Attacked(_, _) = event {
+            if let GotAttacked(_, _) | GotShot(_, _) | Attacked(_, _) = event {
                 if context_has_changed {
                     if let Some(controller) = &mut controller_0 {
                         let _ = controller.set_rumble(0xFFFF, 0x8FFF, 333);
                     }
                 }
             }
-            match event {
-                Died(_) | Won(_) => {
-                    if let Some(controller) = &mut controller_0 {
-                        let _ = controller.set_rumble(0x8FFF, 0xFFFF, 666);
-                    }
-
-                    app_state = AppState::ShowingEnd(*event);
-
-                    continue 'game;
+            if let LeveledUp(_) = event {
+                if let Some(controller) = &mut controller_0 {
+                    let _ = controller.set_rumble(0x8FFF, 0x8FFF, 200);
                 }
-                _ => {
-                    let _ = render_text(
-                        &format!("* {}", event.message(context.current_level)),
-                        Color::WHITE,
-                        &mut info_window,
-                        &font,
-                        &mut canvas,
-                        &texture_creator,
-                    );
+            }
+            if let Died(_) | Won(_) = event {
+                if let Some(controller) = &mut controller_0 {
+                    let _ = controller.set_rumble(0x8FFF, 0xFFFF, 666);
                 }
+
+                app_state = AppState::ShowingEnd(*event);
+
+                continue 'game;
             }
         }
 
-        for y in 0..LEVEL_HEIGHT {
-            for x in 0..LEVEL_WIDTH {
-                let (char, mut fg_color, mut bg_color) = match context.level.tiles[(y, x)] {
-                    Tile::Room => ('.', Color::WHITE, Color::BLACK),
-                    Tile::Item(PickUpItem::HealthBoost) => ('+', Color::RED, Color::BLACK),
-                    Tile::Item(PickUpItem::AttackBoost) => ('/', Color::MAGENTA, Color::BLACK),
-                    Tile::Item(PickUpItem::ShieldBoost) => ('[', Color::CYAN, Color::BLACK),
-                    Tile::Wall => (' ', Color::BLACK, Color::WHITE),
-                    Tile::Door => ('+', Color::BLACK, Color::WHITE),
-                    Tile::Stairs(-1) => ('<', Color::WHITE, Color::BLACK),
-                    Tile::Stairs(1) => (
-                        if context.current_level < 25 { '>' } else { '0' },
-                        Color::WHITE,
-                        Color::BLACK,
-                    ),
-                    _ => (' ', Color::WHITE, Color::BLACK),
-                };
-                if context.level.last_seen[(y, x)] < context.current_turn {
-                    fg_color.a = 100;
-                    bg_color.a = 100;
-                }
-                if context.level.last_seen[(y, x)] > 0 {
-                    let _ = render_char(
-                        char,
-                        fg_color,
-                        bg_color,
-                        Point::new(x, y),
-                        &font,
-                        &mut canvas,
-                        &texture_creator,
-                    );
+        canvas.set_draw_color(Color::BLACK);
+        canvas.clear();
+
+        let mut info_window = render_game(&context, &font, &mut canvas, &texture_creator);
+
+        let prompt = match app_state {
+            AppState::ChoosingItem(command) => Some(format!(
+                "{:?} which item? [1-{}]",
+                command,
+                context.player.inventory.len()
+            )),
+            AppState::ChoosingDirection(command) => {
+                Some(format!("{:?} the door in which direction?", command))
+            }
+            AppState::Targeting(_, targeting) => {
+                render_targeting(targeting, &context, &font, &mut canvas, &texture_creator);
+                Some("Tab = next enemy, arrows = move\nEnter / T = throw, Esc = cancel".to_string())
+            }
+            _ => None,
+        };
+        if let Some(prompt) = prompt {
+            let _ = render_text(
+                &prompt,
+                Color::YELLOW,
+                &mut info_window,
+                &font,
+                &mut canvas,
+                &texture_creator,
+            );
+        }
+
+        canvas.present();
+    }
+
+    let _ = context.replay().save(REPLAY_FILE_NAME);
+}
+
+fn direction_of(keycode: Keycode) -> Option<Point> {
+    match keycode {
+        Keycode::Up | Keycode::Kp8 => Some(Point::new(0, -1)),
+        Keycode::Down | Keycode::Kp2 => Some(Point::new(0, 1)),
+        Keycode::Left | Keycode::Kp4 => Some(Point::new(-1, 0)),
+        Keycode::Right | Keycode::Kp6 => Some(Point::new(1, 0)),
+        Keycode::Kp7 => Some(Point::new(-1, -1)),
+        Keycode::Kp9 => Some(Point::new(1, -1)),
+        Keycode::Kp1 => Some(Point::new(-1, 1)),
+        Keycode::Kp3 => Some(Point::new(1, 1)),
+        _ => None,
+    }
+}
+
+// Maps the number keys 1-9 to the indices 0-8 of the inventory.
+fn item_index_of(keycode: Keycode) -> Option<usize> {
+    [
+        (Keycode::Num1, Keycode::Kp1),
+        (Keycode::Num2, Keycode::Kp2),
+        (Keycode::Num3, Keycode::Kp3),
+        (Keycode::Num4, Keycode::Kp4),
+        (Keycode::Num5, Keycode::Kp5),
+        (Keycode::Num6, Keycode::Kp6),
+        (Keycode::Num7, Keycode::Kp7),
+        (Keycode::Num8, Keycode::Kp8),
+        (Keycode::Num9, Keycode::Kp9),
+    ]
+    .iter()
+    .position(|&(num, kp)| keycode == num || keycode == kp)
+}
+
+fn watch_replay<T: RenderTarget>(
+    mut replay_player: ReplayPlayer,
+    event_pump: &mut EventPump,
+    font: &Font,
+    canvas: &mut Canvas<T>,
+    texture_creator: &TextureCreator<WindowContext>,
+) {
+    let mut app_state = AppState::InGame;
+    let mut paused = false;
+    let mut delay = Duration::from_millis(300);
+    let mut last_step = Instant::now();
+
+    'replay: loop {
+        if let AppState::ShowingEnd(event) = app_state {
+            for event in event_pump.poll_iter() {
+                if let Event::Quit { .. } | Event::KeyDown { .. } = event {
+                    break 'replay;
                 }
             }
+
+            render_end(event, &replay_player.context, font, canvas, texture_creator);
+            canvas.present();
+
+            continue 'replay;
         }
 
-        for enemy in &context.level.enemies {
-            if context.level.last_seen[enemy.position] == context.current_turn {
-                if let Some(char) = enemy.kind.to_string().chars().next() {
-                    let _ = render_char(
-                        char,
-                        Color::WHITE,
-                        Color::BLACK,
-                        enemy.position,
-                        &font,
-                        &mut canvas,
-                        &texture_creator,
-                    );
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Q | Keycode::Escape),
+                    ..
+                } => {
+                    break 'replay;
                 }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Space),
+                    ..
+                } => {
+                    paused = !paused;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Period | Keycode::Right),
+                    ..
+                } => {
+                    replay_player.step();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Plus | Keycode::KpPlus | Keycode::Equals),
+                    ..
+                } => {
+                    delay = max(delay / 2, Duration::from_millis(25));
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Minus | Keycode::KpMinus),
+                    ..
+                } => {
+                    delay = min(delay * 2, Duration::from_millis(2400));
+                }
+                _ => {}
             }
         }
 
-        let _ = render_char(
-            '@',
-            Color::GREEN,
-            Color::BLACK,
-            context.player.position,
-            &font,
-            &mut canvas,
-            &texture_creator,
+        if !paused && last_step.elapsed() >= delay {
+            replay_player.step();
+            last_step = Instant::now();
+        }
+
+        let context = &replay_player.context;
+        if let Some(event) = context
+            .events
+            .iter()
+            .find(|event| matches!(event, Died(_) | Won(_)))
+        {
+            app_state = AppState::ShowingEnd(*event);
+            continue 'replay;
+        }
+
+        canvas.set_draw_color(Color::BLACK);
+        canvas.clear();
+
+        let mut info_window = render_game(context, font, canvas, texture_creator);
+
+        let (played, total) = replay_player.progress();
+        let _ = render_text(
+            &format!(
+                "Replay: {}/{} actions{}\nSpace = pause, . = step\n+/- = speed, Q = quit",
+                played,
+                total,
+                if paused { " (paused)" } else { "" }
+            ),
+            Color::GREY,
+            &mut info_window,
+            font,
+            canvas,
+            texture_creator,
         );
 
         canvas.present();
@@ -0,0 +1,308 @@
+use common::being::experience_for_level;
+use common::event::Event::{self, Died, Won};
+use common::gamecontext::GameContext;
+use common::gear::GearSlot;
+use common::hunger::Hunger;
+use common::monsterkind::Colour;
+use common::pickupitem::PickUpItem;
+use common::point::Point;
+use common::targeting::Targeting;
+use common::tile::{DoorState, Tile};
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, RenderTarget, TextureCreator};
+use sdl2::ttf::Font;
+use sdl2::video::WindowContext;
+
+use crate::rendertext::{render_char, render_text};
+use crate::{TILE_HEIGHT, TILE_WIDTH};
+
+pub fn render_end<T: RenderTarget>(
+    event: Event,
+    context: &GameContext,
+    font: &Font,
+    canvas: &mut Canvas<T>,
+    texture_creator: &TextureCreator<WindowContext>,
+) {
+    canvas.set_draw_color(Color::BLACK);
+    canvas.clear();
+
+    let mut game_window = canvas.viewport();
+
+    let _ = render_text(
+        &format!(
+            "{}{}",
+            event.message(context.current_level),
+            "Press any key to exit the game..."
+        ),
+        Color::WHITE,
+        &mut game_window,
+        font,
+        canvas,
+        texture_creator,
+    );
+}
+
+// Renders the level and the info panel, returning the part of the info panel left empty.
+pub fn render_game<T: RenderTarget>(
+    context: &GameContext,
+    font: &Font,
+    canvas: &mut Canvas<T>,
+    texture_creator: &TextureCreator<WindowContext>,
+) -> Rect {
+    let screen = canvas.viewport();
+    let level_width = TILE_WIDTH * context.level.tiles.width();
+    canvas.set_draw_color(Color::WHITE);
+    let _ = canvas.draw_line((level_width, 0), (level_width, screen.height() as i32));
+
+    let mut info_window = Rect::new(
+        level_width + 1,
+        0,
+        screen.width() - level_width as u32 - 1,
+        screen.height(),
+    );
+
+    let _ = render_text(
+        &format!(
+            "Level {}\nTurn {}",
+            context.current_level, context.current_turn
+        ),
+        Color::WHITE,
+        &mut info_window,
+        font,
+        canvas,
+        texture_creator,
+    );
+    let _ = render_text(
+        &format!(
+            "HP: {}/{}",
+            context.player.health_points, context.player.max_health_points
+        ),
+        Color::RED,
+        &mut info_window,
+        font,
+        canvas,
+        texture_creator,
+    );
+    let _ = render_text(
+        &format!(
+            "XP: {}/{} (level {})",
+            context.player.experience_points,
+            experience_for_level(context.player.experience_level + 1),
+            context.player.experience_level
+        ),
+        Color::GREEN,
+        &mut info_window,
+        font,
+        canvas,
+        texture_creator,
+    );
+    let hunger = Hunger::of(context.player.nutrition);
+    if hunger != Hunger::Fed || context.player.statuses.iter().next().is_some() {
+        let statuses = (hunger != Hunger::Fed)
+            .then(|| hunger.to_string())
+            .into_iter()
+            .chain(
+                context
+                    .player
+                    .statuses
+                    .iter()
+                    .map(|status| status.to_string()),
+            )
+            .collect::<Vec<_>>()
+            .join("\n");
+        let _ = render_text(
+            &statuses,
+            Color::YELLOW,
+            &mut info_window,
+            font,
+            canvas,
+            texture_creator,
+        );
+    }
+    let _ = render_text(
+        &match context.player.equipment.weapon {
+            Some(weapon) => format!(
+                "Attack: 0-{}\nWeapon: {}",
+                context.player.max_attack, weapon
+            ),
+            None => format!("Attack: 0-{}", context.player.max_attack),
+        },
+        Color::MAGENTA,
+        &mut info_window,
+        font,
+        canvas,
+        texture_creator,
+    );
+    let _ = render_text(
+        &match context.player.equipment.armour {
+            Some(armour) => format!(
+                "Shielding: 0-{}\nArmour: {}",
+                context.player.max_shield, armour
+            ),
+            None => format!("Shielding: 0-{}", context.player.max_shield),
+        },
+        Color::CYAN,
+        &mut info_window,
+        font,
+        canvas,
+        texture_creator,
+    );
+
+    if !context.player.inventory.is_empty() {
+        let inventory = context
+            .player
+            .inventory
+            .iter()
+            .enumerate()
+            .map(|(i, item)| format!("{}. {}", i + 1, item))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let _ = render_text(
+            &format!("Inventory:\n{}", inventory),
+            Color::YELLOW,
+            &mut info_window,
+            font,
+            canvas,
+            texture_creator,
+        );
+    }
+
+    for event in &context.events {
+        if let Died(_) | Won(_) = event {
+            continue;
+        }
+        let _ = render_text(
+            &format!("* {}", event.message(context.current_level)),
+            Color::WHITE,
+            &mut info_window,
+            font,
+            canvas,
+            texture_creator,
+        );
+    }
+
+    for y in 0..context.level.tiles.height() {
+        for x in 0..context.level.tiles.width() {
+            let (char, mut fg_color, mut bg_color) = match context.level.tiles[(y, x)].as_seen() {
+                Tile::Room => ('.', Color::WHITE, Color::BLACK),
+                Tile::Item(PickUpItem::HealthBoost) => ('+', Color::RED, Color::BLACK),
+                Tile::Item(PickUpItem::AttackBoost) => ('/', Color::MAGENTA, Color::BLACK),
+                Tile::Item(PickUpItem::ShieldBoost) => ('[', Color::CYAN, Color::BLACK),
+                Tile::Item(PickUpItem::HealingPotion) => ('!', Color::RED, Color::BLACK),
+                Tile::Item(
+                    PickUpItem::BlinkPotion
+                    | PickUpItem::SpeedPotion
+                    | PickUpItem::RegenerationPotion,
+                ) => ('!', Color::YELLOW, Color::BLACK),
+                Tile::Item(
+                    PickUpItem::TeleportScroll
+                    | PickUpItem::MagicMappingScroll
+                    | PickUpItem::FearScroll,
+                ) => ('?', Color::YELLOW, Color::BLACK),
+                Tile::Item(PickUpItem::FoodRation) => ('%', Color::YELLOW, Color::BLACK),
+                Tile::Item(PickUpItem::Key) => ('-', Color::YELLOW, Color::BLACK),
+                Tile::Item(PickUpItem::Gear(gear)) => match gear.slot() {
+                    GearSlot::Weapon => (')', Color::MAGENTA, Color::BLACK),
+                    GearSlot::Armour => (']', Color::CYAN, Color::BLACK),
+                },
+                Tile::Wall => (' ', Color::BLACK, Color::WHITE),
+                Tile::Door(DoorState::Open) => ('\'', Color::BLACK, Color::WHITE),
+                Tile::Door(DoorState::Closed) => ('+', Color::BLACK, Color::WHITE),
+                Tile::Door(DoorState::Locked) => ('+', Color::YELLOW, Color::WHITE),
+                Tile::Trap(_) => ('^', Color::RED, Color::BLACK),
+                Tile::Stairs(-1) => ('<', Color::WHITE, Color::BLACK),
+                Tile::Stairs(1) => (
+                    if context.is_last_level() { '0' } else { '>' },
+                    Color::WHITE,
+                    Color::BLACK,
+                ),
+                _ => (' ', Color::WHITE, Color::BLACK),
+            };
+            if context.level.last_seen[(y, x)] < context.current_turn {
+                fg_color.a = 100;
+                bg_color.a = 100;
+            }
+            if context.level.last_seen[(y, x)] > 0 {
+                let _ = render_char(
+                    char,
+                    fg_color,
+                    bg_color,
+                    Point::new(x, y),
+                    font,
+                    canvas,
+                    texture_creator,
+                );
+            }
+        }
+    }
+
+    for enemy in &context.level.enemies {
+        if context.level.last_seen[enemy.position] == context.current_turn {
+            let _ = render_char(
+                enemy.kind.glyph(),
+                sdl_color_of(enemy.kind.colour()),
+                Color::BLACK,
+                enemy.position,
+                font,
+                canvas,
+                texture_creator,
+            );
+        }
+    }
+
+    let _ = render_char(
+        '@',
+        Color::GREEN,
+        Color::BLACK,
+        context.player.position,
+        font,
+        canvas,
+        texture_creator,
+    );
+
+    info_window
+}
+
+// Draws the path a thrown item would fly along, with the target highlighted.
+pub fn render_targeting<T: RenderTarget>(
+    targeting: Targeting,
+    context: &GameContext,
+    font: &Font,
+    canvas: &mut Canvas<T>,
+    texture_creator: &TextureCreator<WindowContext>,
+) {
+    for point in targeting.path(context) {
+        if point != targeting.target {
+            let _ = render_char(
+                '*',
+                Color::YELLOW,
+                Color::BLACK,
+                point,
+                font,
+                canvas,
+                texture_creator,
+            );
+        }
+    }
+
+    canvas.set_draw_color(Color::YELLOW);
+    let _ = canvas.draw_rect(Rect::new(
+        targeting.target.x * TILE_WIDTH,
+        targeting.target.y * TILE_HEIGHT,
+        TILE_WIDTH as u32,
+        TILE_HEIGHT as u32,
+    ));
+}
+
+fn sdl_color_of(colour: Colour) -> Color {
+    match colour {
+        Colour::Red => Color::RED,
+        Colour::Green => Color::GREEN,
+        Colour::Yellow => Color::YELLOW,
+        Colour::Blue => Color::BLUE,
+        Colour::Magenta => Color::MAGENTA,
+        Colour::Cyan => Color::CYAN,
+        Colour::White => Color::WHITE,
+    }
+}
@@ -1,46 +1,98 @@
 use common::beingkind::BeingKind;
+use common::gear::GearSlot;
+use common::monsterkind::Colour;
 use common::pickupitem::PickUpItem;
-use common::tile::Tile;
+use common::tile::{DoorState, Tile};
 use yew::{classes, html, Html};
 
-pub fn gen_player_cell() -> Html {
+// highlight is an extra class for the cells on a targeting path
+pub fn gen_player_cell(highlight: Option<&'static str>) -> Html {
     html! {
-        <td class="player" title="You">{"@"}</td>
+        <td class={classes!("player", highlight)} title="You">{"@"}</td>
     }
 }
 
-pub fn gen_empty_cell() -> Html {
+pub fn gen_empty_cell(highlight: Option<&'static str>) -> Html {
     html! {
-        <td>{"\u{00a0}"}</td>
+        <td class={classes!(highlight)}>{"\u{00a0}"}</td>
     }
 }
 
-pub fn gen_enemy_cell(kind: BeingKind) -> Html {
+pub fn gen_enemy_cell(kind: BeingKind, highlight: Option<&'static str>) -> Html {
     html! {
-        <td title={kind.to_string()}>
-            {kind.to_string().chars().next().unwrap_or('?')}
+        <td title={kind.to_string()} class={classes!(highlight)} style={css_color_of(kind.colour())}>
+            {kind.glyph()}
         </td>
     }
 }
 
-pub fn gen_cell(tile: Tile, in_fov: bool, level: i32) -> Html {
+fn css_color_of(colour: Colour) -> &'static str {
+    match colour {
+        Colour::Red => "color: red",
+        Colour::Green => "color: green",
+        Colour::Yellow => "color: yellow",
+        Colour::Blue => "color: dodgerblue",
+        Colour::Magenta => "color: magenta",
+        Colour::Cyan => "color: cyan",
+        Colour::White => "color: white",
+    }
+}
+
+pub fn gen_cell(
+    tile: Tile,
+    in_fov: bool,
+    is_last_level: bool,
+    highlight: Option<&'static str>,
+) -> Html {
+    let tile = tile.as_seen();
     html! {
         <td title={tile.get_name()} class={classes!(
+            highlight,
             if in_fov {None} else {Some("out_of_fov")},
-            if tile == Tile::Door || tile == Tile::Wall {Some("door_or_wall")} else {None},
+            if tile.is_door() || tile == Tile::Wall {Some("door_or_wall")} else {None},
+            if tile == Tile::Door(DoorState::Locked) {Some("locked_door")} else {None},
+            if matches!(tile, Tile::Trap(_)) {Some("trap")} else {None},
             if tile == Tile::Item(PickUpItem::HealthBoost) {Some("health_boost")} else {None},
             if tile == Tile::Item(PickUpItem::AttackBoost) {Some("attack_boost")} else {None},
             if tile == Tile::Item(PickUpItem::ShieldBoost) {Some("shield_boost")} else {None},
+            if tile == Tile::Item(PickUpItem::HealingPotion) {Some("health_boost")} else {None},
+            if matches!(tile, Tile::Item(PickUpItem::Gear(gear)) if gear.slot() == GearSlot::Weapon) {Some("attack_boost")} else {None},
+            if matches!(tile, Tile::Item(PickUpItem::Gear(gear)) if gear.slot() == GearSlot::Armour) {Some("shield_boost")} else {None},
+            if matches!(tile, Tile::Item(
+                PickUpItem::BlinkPotion
+                | PickUpItem::SpeedPotion
+                | PickUpItem::RegenerationPotion
+                | PickUpItem::TeleportScroll
+                | PickUpItem::MagicMappingScroll
+                | PickUpItem::FearScroll
+                | PickUpItem::FoodRation
+                | PickUpItem::Key
+            )) {Some("consumable")} else {None},
         )}>
             {match tile {
                 Tile::Room => ".",
                 Tile::Item(PickUpItem::HealthBoost) => "+",
                 Tile::Item(PickUpItem::AttackBoost) => "/",
                 Tile::Item(PickUpItem::ShieldBoost) => "[",
+                Tile::Item(
+                    PickUpItem::HealingPotion
+                    | PickUpItem::BlinkPotion
+                    | PickUpItem::SpeedPotion
+                    | PickUpItem::RegenerationPotion
+                ) => "!",
+                Tile::Item(PickUpItem::FoodRation) => "%",
+                Tile::Item(PickUpItem::Key) => "-",
+                Tile::Item(PickUpItem::Gear(gear)) => match gear.slot() {
+                    GearSlot::Weapon => ")",
+                    GearSlot::Armour => "]",
+                },
+                Tile::Item(_) => "?",
                 Tile::Wall => "\u{00a0}",
-                Tile::Door => "+",
+                Tile::Door(DoorState::Open) => "'",
+                Tile::Door(_) => "+",
+                Tile::Trap(_) => "^",
                 Tile::Stairs(-1) => "<",
-                Tile::Stairs(1) => if level < 25 {">"} else {"0"},
+                Tile::Stairs(1) => if is_last_level {"0"} else {">"},
                 _ => "\u{00a0}"
             }}
         </td>
@@ -1,8 +1,11 @@
+use common::being::experience_for_level;
 use common::event::Event;
 use common::gamecontext::GameContext;
+use common::gear::GearSlot;
+use common::hunger::Hunger;
 use common::playeraction::PlayerAction;
 use common::point::Point;
-use common::{LEVEL_HEIGHT, LEVEL_WIDTH};
+use common::targeting::Targeting;
 use gloo_events::{EventListener, EventListenerOptions};
 use gloo_utils::window;
 use wasm_bindgen::JsCast;
@@ -12,13 +15,36 @@ use crate::gencell::{gen_cell, gen_empty_cell, gen_enemy_cell, gen_player_cell};
 
 mod gencell;
 
+enum Msg {
+    Act(PlayerAction),
+    // Moves the player, or the targeting cursor while throwing
+    Move(Point),
+    // Selects, or throws the chosen item at the target while throwing
+    Select,
+    ChooseThrow(usize),
+    NextTarget,
+    CancelThrow,
+    // Asks for the direction of the door to open or close
+    ChooseDoor(DoorCommand),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum DoorCommand {
+    Open,
+    Close,
+}
+
 struct App {
     game_context: GameContext,
     end_game_event: Option<Event>,
     key_listener: Option<EventListener>,
+    // The inventory index of the item being thrown and where it's aimed at
+    targeting: Option<(usize, Targeting)>,
+    // Whether the next direction picks a door to open or close
+    door_command: Option<DoorCommand>,
 }
 impl Component for App {
-    type Message = PlayerAction;
+    type Message = Msg;
     type Properties = ();
 
     fn create(_ctx: &Context<Self>) -> Self {
@@ -26,16 +52,58 @@ impl Component for App {
             game_context: GameContext::new(),
             end_game_event: None,
             key_listener: None,
+            targeting: None,
+            door_command: None,
         }
     }
 
     fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
-        self.game_context.next_turn(msg);
+        let action = match msg {
+            Msg::Act(action) => action,
+            Msg::Move(direction) => match (&mut self.targeting, self.door_command) {
+                (Some((_, targeting)), _) => {
+                    targeting.move_by(&self.game_context, direction);
+                    return true;
+                }
+                (None, Some(DoorCommand::Open)) => PlayerAction::OpenDoor(direction),
+                (None, Some(DoorCommand::Close)) => PlayerAction::CloseDoor(direction),
+                (None, None) => PlayerAction::MoveBy(direction),
+            },
+            Msg::Select => match self.targeting {
+                Some((index, targeting)) => PlayerAction::ThrowItem(index, targeting.target),
+                None => PlayerAction::Select,
+            },
+            Msg::ChooseThrow(index) => {
+                self.targeting = Some((index, Targeting::new(&self.game_context)));
+                return true;
+            }
+            Msg::NextTarget => {
+                if let Some((_, targeting)) = &mut self.targeting {
+                    targeting.next_enemy(&self.game_context);
+                }
+                return true;
+            }
+            Msg::CancelThrow => {
+                self.targeting = None;
+                self.door_command = None;
+                return true;
+            }
+            Msg::ChooseDoor(command) => {
+                self.targeting = None;
+                self.door_command = Some(command);
+                return true;
+            }
+        };
+        self.targeting = None;
+        self.door_command = None;
+        self.game_context.next_turn(action);
 
-        if let Some(event) = self.game_context.events.iter().find(|event| match event {
-            Event::Died(_) | Event::Won(_) => true,
-            _ => false,
-        }) {
+        if let Some(event) = self
+            .game_context
+            .events
+            .iter()
+            .find(|event| matches!(event, Event::Died(_) | Event::Won(_)))
+        {
             self.end_game_event.replace(*event);
         }
 
@@ -67,17 +135,76 @@ impl Component for App {
             "HP: {}/{}",
             self.game_context.player.health_points, self.game_context.player.max_health_points
         );
-        let xp_string = format!("XP: {}", self.game_context.player.experience_points);
+        let xp_string = format!(
+            "XP: {}/{} (level {})",
+            self.game_context.player.experience_points,
+            experience_for_level(self.game_context.player.experience_level + 1),
+            self.game_context.player.experience_level
+        );
+        let hunger = Hunger::of(self.game_context.player.nutrition);
+        let status_contents = (hunger != Hunger::Fed)
+            .then(|| hunger.to_string())
+            .into_iter()
+            .chain(
+                self.game_context
+                    .player
+                    .statuses
+                    .iter()
+                    .map(|status| status.to_string()),
+            )
+            .map(|status| {
+                html! {
+                    <li>{status}</li>
+                }
+            });
         let attack_string = format!("Attack: 0-{}", self.game_context.player.max_attack);
         let shield_string = format!("Shield: 0-{}", self.game_context.player.max_shield);
 
-        let table_contents = (0..LEVEL_HEIGHT).map(|y| {
-            let table_row = (0..LEVEL_WIDTH).map(|x| {
+        let unequip_weapon_callback =
+            link.callback(|_| Msg::Act(PlayerAction::Unequip(GearSlot::Weapon)));
+        let unequip_armour_callback =
+            link.callback(|_| Msg::Act(PlayerAction::Unequip(GearSlot::Armour)));
+        let weapon_display = self.game_context.player.equipment.weapon.map(|weapon| {
+            html! {
+                <p id="weapon_display">
+                    {format!("Weapon: {}", weapon)}
+                    <button onclick={unequip_weapon_callback}>{"Take off"}</button>
+                </p>
+            }
+        });
+        let armour_display = self.game_context.player.equipment.armour.map(|armour| {
+            html! {
+                <p id="armour_display">
+                    {format!("Armour: {}", armour)}
+                    <button onclick={unequip_armour_callback}>{"Take off"}</button>
+                </p>
+            }
+        });
+
+        let targeting_path = self
+            .targeting
+            .map(|(_, targeting)| targeting.path(&self.game_context))
+            .unwrap_or_default();
+        let target = self.targeting.map(|(_, targeting)| targeting.target);
+
+        let (width, height) = (
+            self.game_context.level.tiles.width(),
+            self.game_context.level.tiles.height(),
+        );
+        let table_contents = (0..height).map(|y| {
+            let table_row = (0..width).map(|x| {
                 let position = Point::new(x, y);
+                let highlight = if target == Some(position) {
+                    Some("target")
+                } else if targeting_path.contains(&position) {
+                    Some("projectile_path")
+                } else {
+                    None
+                };
 
                 if self.game_context.level.last_seen[position] > 0 {
                     if position == self.game_context.player.position {
-                        gen_player_cell()
+                        gen_player_cell(highlight)
                     } else if self.game_context.level.last_seen[position]
                         == self.game_context.current_turn
                     {
@@ -88,23 +215,25 @@ impl Component for App {
                             .iter()
                             .find(|enemy| enemy.position == position)
                         {
-                            gen_enemy_cell(enemy.kind)
+                            gen_enemy_cell(enemy.kind, highlight)
                         } else {
                             gen_cell(
                                 self.game_context.level.tiles[position],
                                 true,
-                                self.game_context.current_level,
+                                self.game_context.is_last_level(),
+                                highlight,
                             )
                         }
                     } else {
                         gen_cell(
                             self.game_context.level.tiles[position],
                             false,
-                            self.game_context.current_level,
+                            self.game_context.is_last_level(),
+                            highlight,
                         )
                     }
                 } else {
-                    gen_empty_cell()
+                    gen_empty_cell(highlight)
                 }
             });
 
@@ -113,13 +242,67 @@ impl Component for App {
             }
         });
 
-        let select_callback = link.callback(|_| PlayerAction::Select);
-        let skip_turn_callback = link.callback(|_| PlayerAction::MoveBy(Point::new(0, 0)));
+        let inventory_contents =
+            self.game_context
+                .player
+                .inventory
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let use_callback = link.callback(move |_| Msg::Act(PlayerAction::UseItem(i)));
+                    let drop_callback = link.callback(move |_| Msg::Act(PlayerAction::DropItem(i)));
+                    let throw_callback = link.callback(move |_| Msg::ChooseThrow(i));
 
-        let move_left_callback = link.callback(|_| PlayerAction::MoveBy(Point::new(-1, 0)));
-        let move_down_callback = link.callback(|_| PlayerAction::MoveBy(Point::new(0, 1)));
-        let move_up_callback = link.callback(|_| PlayerAction::MoveBy(Point::new(0, -1)));
-        let move_right_callback = link.callback(|_| PlayerAction::MoveBy(Point::new(1, 0)));
+                    html! {
+                        <li>
+                            {item.to_string()}
+                            <button onclick={use_callback}>{"Use"}</button>
+                            <button onclick={drop_callback}>{"Drop"}</button>
+                            <button onclick={throw_callback}>{"Throw"}</button>
+                        </li>
+                    }
+                });
+        let next_target_callback = link.callback(|_| Msg::NextTarget);
+        let throw_callback = link.callback(|_| Msg::Select);
+        let cancel_throw_callback = link.callback(|_| Msg::CancelThrow);
+        let throw_prompt = self.targeting.map(|(index, _)| {
+            html! {
+                <p id="throw_prompt">
+                    {format!(
+                        "Aim the {} with the arrows.",
+                        self.game_context.player.inventory[index]
+                    )}
+                    <button onclick={next_target_callback}>{"Next target"}</button>
+                    <button onclick={throw_callback}>{"Throw"}</button>
+                    <button onclick={cancel_throw_callback}>{"Cancel"}</button>
+                </p>
+            }
+        });
+
+        let door_prompt = self.door_command.map(|command| {
+            html! {
+                <p id="door_prompt">
+                    {format!("{:?} the door in which direction?", command)}
+                </p>
+            }
+        });
+
+        let select_callback = link.callback(|_| Msg::Select);
+        let pick_up_callback = link.callback(|_| Msg::Act(PlayerAction::PickUp));
+        let search_callback = link.callback(|_| Msg::Act(PlayerAction::Search));
+        let open_door_callback = link.callback(|_| Msg::ChooseDoor(DoorCommand::Open));
+        let close_door_callback = link.callback(|_| Msg::ChooseDoor(DoorCommand::Close));
+        let skip_turn_callback =
+            link.callback(|_| Msg::Act(PlayerAction::MoveBy(Point::new(0, 0))));
+
+        let move_left_callback = link.callback(|_| Msg::Move(Point::new(-1, 0)));
+        let move_down_callback = link.callback(|_| Msg::Move(Point::new(0, 1)));
+        let move_up_callback = link.callback(|_| Msg::Move(Point::new(0, -1)));
+        let move_right_callback = link.callback(|_| Msg::Move(Point::new(1, 0)));
+        let move_up_left_callback = link.callback(|_| Msg::Move(Point::new(-1, -1)));
+        let move_up_right_callback = link.callback(|_| Msg::Move(Point::new(1, -1)));
+        let move_down_left_callback = link.callback(|_| Msg::Move(Point::new(-1, 1)));
+        let move_down_right_callback = link.callback(|_| Msg::Move(Point::new(1, 1)));
 
         html! {
             <div id="root">
@@ -129,21 +312,42 @@ impl Component for App {
                     <p>{turn_string}</p>
                     <p id="hp_display">{hp_string}</p>
                     <p id="xp_display">{xp_string}</p>
+                    <ul id="status_display">{ for status_contents }</ul>
                     <p id="attack_display">{attack_string}</p>
+                    { for weapon_display }
                     <p id="shield_display">{shield_string}</p>
+                    { for armour_display }
+                    <ul id="inventory_display">{ for inventory_contents }</ul>
+                    { for throw_prompt }
+                    { for door_prompt }
                     <ul id="events_display">{ for events_contents }</ul>
                 </div>
                 <table id="controls">
                     <tr>
-                        <td><button onclick={skip_turn_callback}>{"Skip turn"}</button></td>
+                        <td><button onclick={move_up_left_callback}>{"↖"}</button></td>
                         <td><button onclick={move_up_callback}>{"↑"}</button></td>
-                        <td><button onclick={select_callback}>{"Enter"}</button></td>
+                        <td><button onclick={move_up_right_callback}>{"↗"}</button></td>
                     </tr>
                     <tr>
                         <td><button onclick={move_left_callback}>{"←"}</button></td>
-                        <td><button onclick={move_down_callback}>{"↓"}</button></td>
+                        <td><button onclick={skip_turn_callback}>{"Skip turn"}</button></td>
                         <td><button onclick={move_right_callback}>{"→"}</button></td>
                     </tr>
+                    <tr>
+                        <td><button onclick={move_down_left_callback}>{"↙"}</button></td>
+                        <td><button onclick={move_down_callback}>{"↓"}</button></td>
+                        <td><button onclick={move_down_right_callback}>{"↘"}</button></td>
+                    </tr>
+                    <tr>
+                        <td><button onclick={select_callback}>{"Enter"}</button></td>
+                        <td><button onclick={pick_up_callback}>{"Pick up"}</button></td>
+                        <td><button onclick={search_callback}>{"Search"}</button></td>
+                    </tr>
+                    <tr>
+                        <td><button onclick={open_door_callback}>{"Open"}</button></td>
+                        <td></td>
+                        <td><button onclick={close_door_callback}>{"Close"}</button></td>
+                    </tr>
                 </table>
             </div>
         }
@@ -153,13 +357,24 @@ impl Component for App {
         if first_render {
             let link = ctx.link();
 
-            let select_callback = link.callback(|()| PlayerAction::Select);
-            let skip_turn_callback = link.callback(|()| PlayerAction::MoveBy(Point::new(0, 0)));
+            let select_callback = link.callback(|()| Msg::Select);
+            let next_target_callback = link.callback(|()| Msg::NextTarget);
+            let cancel_throw_callback = link.callback(|()| Msg::CancelThrow);
+            let pick_up_callback = link.callback(|()| Msg::Act(PlayerAction::PickUp));
+            let search_callback = link.callback(|()| Msg::Act(PlayerAction::Search));
+            let open_door_callback = link.callback(|()| Msg::ChooseDoor(DoorCommand::Open));
+            let close_door_callback = link.callback(|()| Msg::ChooseDoor(DoorCommand::Close));
+            let skip_turn_callback =
+                link.callback(|()| Msg::Act(PlayerAction::MoveBy(Point::new(0, 0))));
 
-            let move_left_callback = link.callback(|()| PlayerAction::MoveBy(Point::new(-1, 0)));
-            let move_down_callback = link.callback(|()| PlayerAction::MoveBy(Point::new(0, 1)));
-            let move_up_callback = link.callback(|()| PlayerAction::MoveBy(Point::new(0, -1)));
-            let move_right_callback = link.callback(|()| PlayerAction::MoveBy(Point::new(1, 0)));
+            let move_left_callback = link.callback(|()| Msg::Move(Point::new(-1, 0)));
+            let move_down_callback = link.callback(|()| Msg::Move(Point::new(0, 1)));
+            let move_up_callback = link.callback(|()| Msg::Move(Point::new(0, -1)));
+            let move_right_callback = link.callback(|()| Msg::Move(Point::new(1, 0)));
+            let move_up_left_callback = link.callback(|()| Msg::Move(Point::new(-1, -1)));
+            let move_up_right_callback = link.callback(|()| Msg::Move(Point::new(1, -1)));
+            let move_down_left_callback = link.callback(|()| Msg::Move(Point::new(-1, 1)));
+            let move_down_right_callback = link.callback(|()| Msg::Move(Point::new(1, 1)));
 
             self.key_listener.replace(EventListener::new_with_options(
                 &window(),
@@ -180,18 +395,49 @@ impl Component for App {
                             "ArrowRight" | "l" | "L" => {
                                 move_right_callback.emit(());
                             }
+                            "Home" | "y" | "Y" => {
+                                move_up_left_callback.emit(());
+                            }
+                            "PageUp" | "u" | "U" => {
+                                move_up_right_callback.emit(());
+                            }
+                            "End" | "b" | "B" => {
+                                move_down_left_callback.emit(());
+                            }
+                            "PageDown" | "n" | "N" => {
+                                move_down_right_callback.emit(());
+                            }
                             " " => {
                                 skip_turn_callback.emit(());
                             }
                             "Enter" => {
                                 select_callback.emit(());
                             }
+                            "g" | "," => {
+                                pick_up_callback.emit(());
+                            }
+                            "s" => {
+                                search_callback.emit(());
+                            }
+                            "o" => {
+                                open_door_callback.emit(());
+                            }
+                            "c" => {
+                                close_door_callback.emit(());
+                            }
+                            "Tab" => {
+                                event.prevent_default();
+                                next_target_callback.emit(());
+                            }
+                            "Escape" => {
+                                cancel_throw_callback.emit(());
+                            }
                             _ => {}
                         }
                     }
                 },
             ));
-        } else if let Some(_) = self.end_game_event {
+        } else if self.end_game_event.is_some() {
             self.key_listener.take();
         }
     }